@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter};
 
 use crate::config::load_config;
+use crate::storage::{MemoryCategory, MemoryFact, MemoryOperation, DEFAULT_MEMORY_IMPORTANCE};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatMessage {
@@ -17,6 +18,26 @@ pub struct StreamToken {
     pub done: bool,
 }
 
+/// Sampling options passed through to Ollama's `options`; unset fields use the model's defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+}
+
 #[derive(Debug, Deserialize)]
 struct OllamaChatChunk {
     message: Option<OllamaChatMsg>,
@@ -34,18 +55,21 @@ pub async fn send_chat_message(
     messages: Vec<ChatMessage>,
     model: String,
     memory_context: String,
+    system_prompt: Option<String>,
+    options: GenerationOptions,
 ) -> Result<String, String> {
     let config = load_config();
     let url = format!("{}/api/chat", config.ollama_host);
     let client = Client::new();
 
-    // Build system prompt: memory context + user's custom system prompt
+    // Build system prompt: memory context + the persona's prompt, or the user's custom system prompt
+    let system_prompt = system_prompt.unwrap_or_else(|| config.system_prompt.clone());
     let mut system_parts = Vec::new();
     if !memory_context.is_empty() {
         system_parts.push(memory_context);
     }
-    if !config.system_prompt.is_empty() {
-        system_parts.push(config.system_prompt.clone());
+    if !system_prompt.is_empty() {
+        system_parts.push(system_prompt);
     }
 
     // Build message history including system prompt
@@ -68,7 +92,8 @@ pub async fn send_chat_message(
         .json(&serde_json::json!({
             "model": model,
             "messages": ollama_messages,
-            "stream": true
+            "stream": true,
+            "options": options
         }))
         .send()
         .await
@@ -131,13 +156,19 @@ pub async fn send_chat_message(
     Ok(full_response)
 }
 
-/// Analyze the latest messages and extract new personal facts about the user.
-/// Returns a list of concise fact strings. This uses a non-streaming LLM call.
+/// Number of trailing messages the fact extractor looks at
+pub const FACT_EXTRACTION_WINDOW: usize = 4;
+
+/// Analyze the latest messages and decide how long-term memory should change:
+/// add new facts, update existing ones that are now outdated, or delete ones that
+/// were contradicted. `existing_memories` is `(id, content)`; `pending` holds facts
+/// still awaiting review so they aren't proposed twice. This uses a non-streaming LLM call.
 pub async fn extract_facts_from_conversation(
     messages: &[ChatMessage],
     model: &str,
-    existing_memories: &[String],
-) -> Result<Vec<String>, String> {
+    existing_memories: &[(String, String)],
+    pending: &[String],
+) -> Result<Vec<MemoryOperation>, String> {
     let config = load_config();
     let url = format!("{}/api/chat", config.ollama_host);
     let client = Client::builder()
@@ -146,7 +177,7 @@ pub async fn extract_facts_from_conversation(
         .map_err(|e| format!("HTTP client error: {}", e))?;
 
     // Only look at the last few messages for efficiency
-    let recent: Vec<&ChatMessage> = messages.iter().rev().take(4).collect::<Vec<_>>().into_iter().rev().collect();
+    let recent: Vec<&ChatMessage> = messages.iter().rev().take(FACT_EXTRACTION_WINDOW).collect::<Vec<_>>().into_iter().rev().collect();
 
     // Build the conversation excerpt
     let mut excerpt = String::new();
@@ -155,35 +186,56 @@ pub async fn extract_facts_from_conversation(
         excerpt.push_str(&format!("{}: {}\n", label, msg.content));
     }
 
-    // Build existing memories context so we don't duplicate
-    let existing_str = if existing_memories.is_empty() {
+    // Existing memories are numbered so the model can refer to them without seeing our IDs
+    let mut existing_str = if existing_memories.is_empty() {
         "None yet.".to_string()
     } else {
-        existing_memories.iter().map(|m| format!("- {}", m)).collect::<Vec<_>>().join("\n")
+        existing_memories
+            .iter()
+            .enumerate()
+            .map(|(i, (_, content))| format!("[{}] {}", i + 1, content))
+            .collect::<Vec<_>>()
+            .join("\n")
     };
+    if !pending.is_empty() {
+        existing_str.push_str("\n\nAlready proposed, awaiting review (do not propose again):\n");
+        existing_str.push_str(&pending.iter().map(|m| format!("- {}", m)).collect::<Vec<_>>().join("\n"));
+    }
 
     let system_prompt = format!(
-        r#"You are a strict personal fact extractor. Analyze the conversation below and extract ONLY permanent, long-term personal facts about the user.
+        r#"You are a strict personal fact extractor. Analyze the conversation below and decide how the list of long-term personal facts about the user must change.
 
 Rules:
-- ONLY extract core identity facts: Name, occupation, location, family members, pets, allergies, or major long-term hobbies.
-- Do NOT extract short-term interests, current goals, shopping preferences, budget, opinions, or conversational context. (e.g., "Interested in OLED TVs" or "Wants to buy a TV" are short-term context, NOT long-term facts).
+- ONLY track durable facts in these categories: identity (name, age, location), preferences (lasting likes, dislikes, dietary choices), work (occupation, employer, skills), health (allergies, conditions) and relationships (family, partners, friends, pets).
+- Do NOT extract passing interests, shopping preferences, budget, opinions, or conversational context. (e.g., "Interested in OLED TVs" or "Wants to buy a TV" are short-term context, NOT long-term facts).
 - Each fact must be a single short sentence (under 100 characters).
+- Rate importance from 1 (minor detail) to 5 (essential, e.g. a serious allergy).
+- If a fact is only true until a known date (e.g. travelling until Friday), set "expires_at" to that date as YYYY-MM-DD; otherwise use null. Today is {}.
+- If a known fact is now outdated (e.g. the user moved or changed jobs), UPDATE it instead of adding a new one.
+- If the user says a known fact is wrong or no longer true, DELETE it.
 - Do NOT repeat facts already known (listed below).
-- If there are NO new permanent facts, respond with exactly: NONE.
-- Output one fact per line, no bullets, no numbering.
 
-Already known facts:
+Respond with JSON only, in this exact shape:
+{{"operations": [
+  {{"op": "add", "content": "<new fact>", "category": "<identity|preferences|work|health|relationships>", "importance": <1-5>, "expires_at": null}},
+  {{"op": "update", "ref": <number of known fact>, "content": "<corrected fact>", "category": "...", "importance": <1-5>, "expires_at": null}},
+  {{"op": "delete", "ref": <number of known fact>}}
+]}}
+If nothing changes, respond with {{"operations": []}}.
+
+Known facts:
 {}
 
 Conversation:
 {}"#,
-        existing_str, excerpt
+        chrono::Utc::now().format("%Y-%m-%d"),
+        existing_str,
+        excerpt
     );
 
     let ollama_messages = vec![
         serde_json::json!({"role": "system", "content": system_prompt}),
-        serde_json::json!({"role": "user", "content": "Extract new personal facts from the conversation above."}),
+        serde_json::json!({"role": "user", "content": "Update the known facts based on the conversation above."}),
     ];
 
     let resp = client
@@ -192,6 +244,7 @@ Conversation:
             "model": model,
             "messages": ollama_messages,
             "stream": false,
+            "format": "json",
             "options": {
                 "temperature": 0.1
             }
@@ -219,18 +272,108 @@ Conversation:
 
     eprintln!("[openworld] Fact extraction raw response: {}", response_text);
 
-    // Parse facts: one per line, skip empty/NONE
-    if response_text.is_empty() || response_text.to_uppercase().contains("NONE") {
-        return Ok(vec![]);
+    Ok(parse_memory_operations(&response_text, existing_memories))
+}
+
+/// Turn the extractor's reply into validated operations. References to known facts
+/// are 1-based indexes into `existing_memories`; unknown references are dropped.
+/// A reply that isn't valid JSON (e.g. cut off mid-object) yields no operations, so
+/// fragments of it are never stored as facts.
+fn parse_memory_operations(response_text: &str, existing_memories: &[(String, String)]) -> Vec<MemoryOperation> {
+    #[derive(Deserialize)]
+    struct RawOperations {
+        #[serde(default)]
+        operations: Vec<RawOperation>,
+    }
+
+    #[derive(Deserialize)]
+    struct RawOperation {
+        op: String,
+        #[serde(default)]
+        content: Option<String>,
+        #[serde(default, rename = "ref")]
+        reference: Option<serde_json::Value>,
+        #[serde(default)]
+        category: Option<String>,
+        #[serde(default)]
+        importance: Option<serde_json::Value>,
+        #[serde(default)]
+        expires_at: Option<String>,
     }
 
-    let facts: Vec<String> = response_text
-        .lines()
-        .map(|l| l.trim().trim_start_matches('-').trim_start_matches('•').trim().to_string())
-        .filter(|l| !l.is_empty() && l.len() <= 150 && !l.to_uppercase().contains("NONE"))
-        .collect();
+    let valid_fact = |c: &str| !c.is_empty() && c.len() <= 150;
+
+    let json = match (response_text.find('{'), response_text.rfind('}')) {
+        (Some(start), Some(end)) if end > start => &response_text[start..=end],
+        _ => "",
+    };
 
-    Ok(facts)
+    let raw = match serde_json::from_str::<RawOperations>(json) {
+        Ok(raw) => raw,
+        Err(e) => {
+            eprintln!("[openworld] Fact extraction reply is not valid JSON, ignoring it: {}", e);
+            return vec![];
+        }
+    };
+
+    let mut touched = std::collections::HashSet::new();
+    let mut ops = Vec::new();
+    for raw_op in raw.operations {
+        let content = raw_op.content.map(|c| c.trim().to_string()).unwrap_or_default();
+        let target = raw_op
+            .reference
+            .and_then(|r| match r {
+                serde_json::Value::Number(n) => n.as_u64(),
+                serde_json::Value::String(s) => s.trim_matches(|c| c == '[' || c == ']').trim().parse().ok(),
+                _ => None,
+            })
+            .and_then(|n| (n as usize).checked_sub(1))
+            .and_then(|i| existing_memories.get(i))
+            .map(|(id, _)| id.clone());
+
+        let importance = raw_op
+            .importance
+            .and_then(|v| match v {
+                serde_json::Value::Number(n) => n.as_f64(),
+                serde_json::Value::String(s) => s.trim().parse().ok(),
+                _ => None,
+            })
+            .map(|n| n.round().clamp(1.0, 5.0) as u8)
+            .unwrap_or(DEFAULT_MEMORY_IMPORTANCE);
+        let fact = MemoryFact {
+            content,
+            category: raw_op.category.as_deref().map(MemoryCategory::parse).unwrap_or_default(),
+            importance,
+            expires_at: raw_op.expires_at.as_deref().and_then(normalize_expiry),
+        };
+
+        let op = match (raw_op.op.to_lowercase().as_str(), target) {
+            ("add", _) if valid_fact(&fact.content) => MemoryOperation::Add { fact },
+            ("update", Some(id)) if valid_fact(&fact.content) => MemoryOperation::Update { id, fact },
+            ("delete", Some(id)) => MemoryOperation::Delete { id },
+            _ => continue,
+        };
+        // Only one change per known fact
+        if let MemoryOperation::Update { id, .. } | MemoryOperation::Delete { id } = &op {
+            if !touched.insert(id.clone()) {
+                continue;
+            }
+        }
+        ops.push(op);
+    }
+    ops
+}
+
+/// Accept RFC 3339 timestamps or plain dates (taken as end of that day, UTC)
+pub fn normalize_expiry(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(raw) {
+        return Some(dt.with_timezone(&chrono::Utc).to_rfc3339());
+    }
+    chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(23, 59, 59))
+        .map(|dt| dt.and_utc().to_rfc3339())
 }
 
 /// Automatically generate a short, concise conversation title based on the first prompt
@@ -294,3 +437,62 @@ pub async fn generate_conversation_title(
 
     Ok(title.trim().trim_matches('"').to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn known() -> Vec<(String, String)> {
+        vec![
+            ("id-a".to_string(), "Lives in Berlin".to_string()),
+            ("id-b".to_string(), "Works as a teacher".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_parse_structured_operations() {
+        let reply = r#"{"operations": [
+            {"op": "update", "ref": 1, "content": "Lives in Lisbon", "category": "identity", "importance": 4},
+            {"op": "delete", "ref": "2"},
+            {"op": "add", "content": "Has a dog named Rex", "category": "Relationships", "importance": 9, "expires_at": null},
+            {"op": "delete", "ref": 7}
+        ]}"#;
+        let ops = parse_memory_operations(reply, &known());
+        assert_eq!(
+            ops,
+            vec![
+                MemoryOperation::Update {
+                    id: "id-a".to_string(),
+                    fact: MemoryFact { category: MemoryCategory::Identity, importance: 4, ..MemoryFact::new("Lives in Lisbon") },
+                },
+                MemoryOperation::Delete { id: "id-b".to_string() },
+                MemoryOperation::Add {
+                    fact: MemoryFact { category: MemoryCategory::Relationships, importance: 5, ..MemoryFact::new("Has a dog named Rex") },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_ignores_invalid_replies() {
+        let truncated = r#"{"operations": [
+            {"op": "add", "content": "Has two cats", "category": "relationships"},
+            {"op": "add", "content": "Name is"#;
+        assert!(parse_memory_operations(truncated, &known()).is_empty());
+        assert!(parse_memory_operations("- Name is Sam
+- Has two cats", &known()).is_empty());
+        assert!(parse_memory_operations("NONE", &known()).is_empty());
+    }
+
+    #[test]
+    fn test_parse_expiry_dates() {
+        let reply = r#"{"operations": [{"op": "add", "content": "Travelling in Japan", "expires_at": "2030-05-02"}]}"#;
+        match &parse_memory_operations(reply, &known())[0] {
+            MemoryOperation::Add { fact } => {
+                assert_eq!(fact.expires_at.as_deref(), Some("2030-05-02T23:59:59+00:00"));
+                assert_eq!(fact.category, MemoryCategory::Other);
+            }
+            other => panic!("unexpected operation: {:?}", other),
+        }
+    }
+}
@@ -1,14 +1,35 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use std::sync::RwLock;
+
+lazy_static::lazy_static! {
+    /// Directory of the active profile; `None` means the data directory itself (default profile)
+    static ref PROFILE_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppConfig {
     pub theme: String,
     pub default_model: String,
     pub setup_complete: bool,
     pub system_prompt: String,
     pub ollama_host: String,
+    /// Days a conversation stays in the trash before it is purged (0 = until emptied manually)
+    pub trash_retention_days: u32,
+    /// Move unpinned conversations not updated for this many days to the trash (None = keep forever)
+    pub conversation_retention_days: Option<u32>,
+    /// Queue extracted facts for approval instead of saving them straight to memory
+    pub memory_review_mode: bool,
+    /// Smaller model for fact extraction and title generation (None = use the chat model)
+    pub utility_model: Option<String>,
+    /// Hold background jobs while a chat reply is streaming
+    pub pause_jobs_while_chatting: bool,
+    /// Ollama model used to embed memories for semantic deduplication
+    pub embedding_model: String,
+    /// Cosine similarity above which two memories count as the same fact
+    pub memory_similarity_threshold: f32,
 }
 
 impl Default for AppConfig {
@@ -19,6 +40,55 @@ impl Default for AppConfig {
             setup_complete: false,
             system_prompt: String::new(),
             ollama_host: "http://localhost:11434".to_string(),
+            trash_retention_days: 30,
+            conversation_retention_days: None,
+            memory_review_mode: false,
+            utility_model: None,
+            pause_jobs_while_chatting: true,
+            embedding_model: "nomic-embed-text".to_string(),
+            memory_similarity_threshold: 0.88,
+        }
+    }
+}
+
+/// Settings of the managed AI engine. There is a single engine for the whole app, so
+/// these live in the data directory and are shared by every profile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineConfig {
+    /// Where the engine is reached; the port the managed engine ends up on is written back here
+    pub ollama_host: String,
+    /// Use an Ollama this app didn't start (a system install) if one answers at `ollama_host`,
+    /// instead of starting the managed engine on a free port
+    pub use_external_engine: bool,
+    /// Ollama release to install and stay on, e.g. "v0.5.7" (None = follow the latest release)
+    pub pinned_engine_version: Option<String>,
+    /// Port for the managed engine (None = keep the current one if it's free, otherwise pick a free port)
+    pub engine_port: Option<u16>,
+    /// Where the managed engine stores models (`OLLAMA_MODELS`; None = Ollama's default `~/.ollama/models`)
+    pub engine_models_dir: Option<String>,
+    /// Requests each loaded model serves at once (`OLLAMA_NUM_PARALLEL`)
+    pub engine_num_parallel: Option<u32>,
+    /// Models kept in memory at the same time (`OLLAMA_MAX_LOADED_MODELS`)
+    pub engine_max_loaded_models: Option<u32>,
+    /// How long an idle model stays loaded, e.g. "5m" or "-1" for forever (`OLLAMA_KEEP_ALIVE`)
+    pub engine_keep_alive: Option<String>,
+    /// Default context window in tokens (`OLLAMA_CONTEXT_LENGTH`)
+    pub engine_context_length: Option<u32>,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            ollama_host: AppConfig::default().ollama_host,
+            use_external_engine: false,
+            pinned_engine_version: None,
+            engine_port: None,
+            engine_models_dir: None,
+            engine_num_parallel: None,
+            engine_max_loaded_models: None,
+            engine_keep_alive: None,
+            engine_context_length: None,
         }
     }
 }
@@ -30,25 +100,76 @@ pub fn get_data_dir() -> PathBuf {
     data_dir
 }
 
+/// Directory holding the active profile's database, keyfile and config
+pub fn profile_dir() -> PathBuf {
+    PROFILE_DIR
+        .read()
+        .ok()
+        .and_then(|dir| dir.clone())
+        .unwrap_or_else(get_data_dir)
+}
+
+pub fn set_profile_dir(dir: PathBuf) {
+    if let Ok(mut current) = PROFILE_DIR.write() {
+        *current = Some(dir);
+    }
+}
+
 fn config_path() -> PathBuf {
-    get_data_dir().join("config.json")
+    profile_dir().join("config.json")
 }
 
+/// Config of the active profile. `ollama_host` always comes from the engine config,
+/// since every profile talks to the same engine.
 pub fn load_config() -> AppConfig {
-    let path = config_path();
+    let mut config = load_config_from(&config_path());
+    config.ollama_host = load_engine_config().ollama_host;
+    config
+}
+
+/// Config stored at `path`, written with defaults if it doesn't exist yet
+pub fn load_config_from(path: &std::path::Path) -> AppConfig {
     if path.exists() {
-        let content = fs::read_to_string(&path).unwrap_or_default();
+        let content = fs::read_to_string(path).unwrap_or_default();
         serde_json::from_str(&content).unwrap_or_default()
     } else {
         let config = AppConfig::default();
-        save_config(&config).ok();
+        save_config_to(path, &config).ok();
         config
     }
 }
 
 pub fn save_config(config: &AppConfig) -> Result<(), String> {
-    let path = config_path();
+    save_config_to(&config_path(), config)
+}
+
+pub fn save_config_to(path: &std::path::Path, config: &AppConfig) -> Result<(), String> {
     let json = serde_json::to_string_pretty(config)
         .map_err(|e| format!("Failed to serialize config: {}", e))?;
     fs::write(path, json).map_err(|e| format!("Failed to write config: {}", e))
 }
+
+fn engine_config_path() -> PathBuf {
+    get_data_dir().join("engine_config.json")
+}
+
+/// Engine settings shared by all profiles. The first time, they are taken over from the
+/// default profile's `config.json`, where older versions kept them.
+pub fn load_engine_config() -> EngineConfig {
+    let path = engine_config_path();
+    if let Ok(content) = fs::read_to_string(&path) {
+        return serde_json::from_str(&content).unwrap_or_default();
+    }
+    let config: EngineConfig = fs::read_to_string(get_data_dir().join("config.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    save_engine_config(&config).ok();
+    config
+}
+
+pub fn save_engine_config(config: &EngineConfig) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize engine config: {}", e))?;
+    fs::write(engine_config_path(), json).map_err(|e| format!("Failed to write engine config: {}", e))
+}
@@ -1,9 +1,13 @@
 use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
 use ring::hkdf;
+use ring::pbkdf2;
 use ring::rand::{SecureRandom, SystemRandom};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 
 const HKDF_INFO: &[u8] = b"openworld-encryption-key";
+const PBKDF2_ITERATIONS: u32 = 210_000;
+/// Known plaintext encrypted with a passphrase-derived key, used to tell a wrong passphrase apart
+const PASSPHRASE_CHECK: &str = "openworld-passphrase-check";
 
 pub struct CryptoEngine {
     key: LessSafeKey,
@@ -102,6 +106,46 @@ pub fn get_or_create_master_secret(data_dir: &std::path::Path) -> Result<Vec<u8>
     }
 }
 
+/// Derive the master secret from a passphrase instead of a keyfile. A random salt is
+/// kept in `.salt` and a check value in `.passcheck`, so a wrong passphrase is rejected
+/// up front rather than failing on the first decryption.
+pub fn get_or_create_passphrase_secret(data_dir: &std::path::Path, passphrase: &str) -> Result<Vec<u8>, String> {
+    let salt_file = data_dir.join(".salt");
+    let check_file = data_dir.join(".passcheck");
+
+    let salt = if salt_file.exists() {
+        std::fs::read(&salt_file).map_err(|e| format!("Failed to read salt: {}", e))?
+    } else {
+        let mut salt = vec![0u8; 16];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|e| format!("RNG failed: {}", e))?;
+        std::fs::write(&salt_file, &salt).map_err(|e| format!("Failed to write salt: {}", e))?;
+        salt
+    };
+
+    let mut secret = vec![0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        std::num::NonZeroU32::new(PBKDF2_ITERATIONS).expect("iterations > 0"),
+        &salt,
+        passphrase.as_bytes(),
+        &mut secret,
+    );
+
+    let engine = CryptoEngine::new(&secret)?;
+    if check_file.exists() {
+        let check = std::fs::read_to_string(&check_file).map_err(|e| format!("Failed to read passphrase check: {}", e))?;
+        if engine.decrypt(check.trim()).ok().as_deref() != Some(PASSPHRASE_CHECK) {
+            return Err("Incorrect passphrase".to_string());
+        }
+    } else {
+        std::fs::write(&check_file, engine.encrypt(PASSPHRASE_CHECK)?)
+            .map_err(|e| format!("Failed to write passphrase check: {}", e))?;
+    }
+    Ok(secret)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -122,4 +166,17 @@ mod tests {
         let e2 = engine.encrypt("same text").unwrap();
         assert_ne!(e1, e2); // Different nonces = different ciphertexts
     }
+
+    #[test]
+    fn test_passphrase_secret_rejects_wrong_passphrase() {
+        let dir = std::env::temp_dir().join(format!("openworld-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let first = get_or_create_passphrase_secret(&dir, "correct horse").unwrap();
+        let again = get_or_create_passphrase_secret(&dir, "correct horse").unwrap();
+        assert_eq!(first, again);
+        assert!(get_or_create_passphrase_secret(&dir, "battery staple").is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
 }
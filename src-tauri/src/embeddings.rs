@@ -0,0 +1,443 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::config::{load_config, AppConfig};
+use crate::storage::{Memory, MemoryCategory, MemoryFact, MemoryOperation, StorageEngine};
+
+/// Above this similarity a new fact is the same as a known one and is dropped outright
+const DUPLICATE_SIMILARITY: f32 = 0.97;
+
+/// A group of memories that say (nearly) the same thing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryMergeProposal {
+    pub memory_ids: Vec<String>,
+    pub contents: Vec<String>,
+    /// Suggested text for the merged memory
+    pub merged_content: String,
+}
+
+/// Embed a batch of texts with Ollama's `/api/embed`
+pub async fn embed(texts: &[String], model: &str) -> Result<Vec<Vec<f32>>, String> {
+    if texts.is_empty() {
+        return Ok(Vec::new());
+    }
+    let config = load_config();
+    let url = format!("{}/api/embed", config.ollama_host);
+    let client = Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .map_err(|e| format!("HTTP client error: {}", e))?;
+
+    let resp = client
+        .post(&url)
+        .json(&serde_json::json!({ "model": model, "input": texts }))
+        .send()
+        .await
+        .map_err(|e| format!("Embedding request failed: {}", e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Embedding request failed with status {}", resp.status()));
+    }
+
+    #[derive(Deserialize)]
+    struct EmbedResponse {
+        embeddings: Vec<Vec<f32>>,
+    }
+
+    let body: EmbedResponse = resp
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse embedding response: {}", e))?;
+    if body.embeddings.len() != texts.len() {
+        return Err("Embedding response did not match the request".to_string());
+    }
+    Ok(body.embeddings)
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// Embeddings of all stored memories as `(id, content, embedding)`, computing and
+/// caching any that are missing or were made with a different model.
+pub async fn memory_embeddings(storage: &StorageEngine, model: &str) -> Result<Vec<(String, String, Vec<f32>)>, String> {
+    let stored = storage.memory_embeddings(model)?;
+
+    let missing: Vec<usize> = stored
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, _, e))| e.is_none())
+        .map(|(i, _)| i)
+        .collect();
+    let texts: Vec<String> = missing.iter().map(|&i| stored[i].1.clone()).collect();
+    let computed = embed(&texts, model).await?;
+
+    for (&i, embedding) in missing.iter().zip(&computed) {
+        storage.set_memory_embedding(&stored[i].0, model, embedding)?;
+    }
+
+    let mut computed = computed.into_iter();
+    Ok(stored
+        .into_iter()
+        .filter_map(|(id, content, embedding)| {
+            let embedding = embedding.or_else(|| computed.next())?;
+            Some((id, content, embedding))
+        })
+        .collect())
+}
+
+/// Stored memories with their embeddings
+async fn embedded_memories(storage: &StorageEngine, model: &str) -> Result<Vec<(Memory, Vec<f32>)>, String> {
+    let mut embeddings: HashMap<String, Vec<f32>> = memory_embeddings(storage, model)
+        .await?
+        .into_iter()
+        .map(|(id, _, embedding)| (id, embedding))
+        .collect();
+    Ok(storage
+        .list_memories()?
+        .into_iter()
+        .filter_map(|memory| {
+            let embedding = embeddings.remove(&memory.id)?;
+            Some((memory, embedding))
+        })
+        .collect())
+}
+
+/// Check newly extracted facts against stored memories before they are saved.
+/// Falls back to the unfiltered operations if embeddings are unavailable.
+pub async fn deduplicate(storage: &StorageEngine, config: &AppConfig, ops: Vec<MemoryOperation>) -> Vec<MemoryOperation> {
+    let result = async {
+        let existing = embedded_memories(storage, &config.embedding_model).await?;
+        let new_texts: Vec<String> = ops
+            .iter()
+            .filter_map(|op| match op {
+                MemoryOperation::Add { fact } => Some(fact.content.clone()),
+                _ => None,
+            })
+            .collect();
+        let new_embeddings = embed(&new_texts, &config.embedding_model).await?;
+        Ok::<_, String>(deduplicate_operations(
+            ops.clone(),
+            &new_embeddings,
+            &existing,
+            config.memory_similarity_threshold,
+        ))
+    }
+    .await;
+
+    match result {
+        Ok(deduped) => {
+            if deduped != ops {
+                // Only counts and ids; memory contents stay out of the logs
+                let adds = |ops: &[MemoryOperation]| ops.iter().filter(|op| matches!(op, MemoryOperation::Add { .. })).count();
+                let merged_into: Vec<&str> = deduped
+                    .iter()
+                    .filter_map(|op| match op {
+                        MemoryOperation::Update { id, .. } if !ops.contains(op) => Some(id.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                eprintln!(
+                    "[openworld] Semantic deduplication kept {} of {} new fact(s), merged into memories {:?}",
+                    adds(&deduped),
+                    adds(&ops),
+                    merged_into
+                );
+            }
+            deduped
+        }
+        Err(e) => {
+            eprintln!("[openworld] Semantic deduplication skipped (non-fatal): {}", e);
+            ops
+        }
+    }
+}
+
+/// Where a single new fact belongs, see `deduplicate_fact`
+#[derive(Debug, Clone, PartialEq)]
+pub enum FactDedup {
+    /// Nothing similar is stored
+    New(MemoryFact),
+    /// Restates memory `id`; `fact` is what to update it with
+    Merge { id: String, fact: MemoryFact },
+    /// Memory `id` already says this, at least as strongly
+    Duplicate { id: String },
+}
+
+/// Check one fact the user adds or approves against stored memories. Falls back to
+/// saving it as new if embeddings are unavailable.
+pub async fn deduplicate_fact(storage: &StorageEngine, config: &AppConfig, fact: MemoryFact) -> FactDedup {
+    let result = async {
+        let existing = embedded_memories(storage, &config.embedding_model).await?;
+        let embedding = embed(std::slice::from_ref(&fact.content), &config.embedding_model)
+            .await?
+            .pop()
+            .ok_or("Embedding response did not match the request")?;
+        Ok::<_, String>(deduplicate_single(fact.clone(), &embedding, &existing, config.memory_similarity_threshold))
+    }
+    .await;
+
+    match result {
+        Ok(deduped) => {
+            match &deduped {
+                FactDedup::Merge { id, .. } => eprintln!("[openworld] Semantic deduplication merged the fact into memory {}", id),
+                FactDedup::Duplicate { id } => eprintln!("[openworld] Semantic deduplication found the fact already in memory {}", id),
+                FactDedup::New(_) => {}
+            }
+            deduped
+        }
+        Err(e) => {
+            eprintln!("[openworld] Semantic deduplication skipped (non-fatal): {}", e);
+            FactDedup::New(fact)
+        }
+    }
+}
+
+fn nearest<'a>(embedding: &[f32], existing: &'a [(Memory, Vec<f32>)]) -> Option<(&'a Memory, f32)> {
+    existing
+        .iter()
+        .map(|(memory, e)| (memory, cosine_similarity(e, embedding)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// `fact` as an update of `existing`: a merge shouldn't weaken what was stored, so the
+/// more specific category, the higher importance and any known expiry are kept
+fn merge_attributes(existing: &Memory, fact: MemoryFact) -> MemoryFact {
+    MemoryFact {
+        category: if existing.category == MemoryCategory::Other { fact.category } else { existing.category },
+        importance: existing.importance.max(fact.importance),
+        expires_at: fact.expires_at.or_else(|| existing.expires_at.clone()),
+        content: fact.content,
+    }
+}
+
+fn deduplicate_single(fact: MemoryFact, embedding: &[f32], existing: &[(Memory, Vec<f32>)], threshold: f32) -> FactDedup {
+    match nearest(embedding, existing) {
+        Some((memory, similarity)) if similarity >= DUPLICATE_SIMILARITY => {
+            // Same text; only save if the new fact strengthens the stored one
+            let merged = merge_attributes(memory, MemoryFact { content: memory.content.clone(), ..fact });
+            let unchanged = merged.category == memory.category
+                && merged.importance == memory.importance
+                && merged.expires_at == memory.expires_at;
+            if unchanged {
+                FactDedup::Duplicate { id: memory.id.clone() }
+            } else {
+                FactDedup::Merge { id: memory.id.clone(), fact: merged }
+            }
+        }
+        Some((memory, similarity)) if similarity >= threshold => FactDedup::Merge {
+            id: memory.id.clone(),
+            fact: merge_attributes(memory, fact),
+        },
+        _ => FactDedup::New(fact),
+    }
+}
+
+/// `new_embeddings` holds one embedding per `Add` in `ops`, in order. An add that is
+/// nearly identical to a known or earlier new fact is dropped; one above `threshold`
+/// becomes an update of the closest known memory (keeping its version history).
+fn deduplicate_operations(
+    ops: Vec<MemoryOperation>,
+    new_embeddings: &[Vec<f32>],
+    existing: &[(Memory, Vec<f32>)],
+    threshold: f32,
+) -> Vec<MemoryOperation> {
+    let mut touched: HashSet<String> = ops
+        .iter()
+        .filter_map(|op| match op {
+            MemoryOperation::Update { id, .. } | MemoryOperation::Delete { id } => Some(id.clone()),
+            MemoryOperation::Add { .. } => None,
+        })
+        .collect();
+    let mut accepted: Vec<&[f32]> = Vec::new();
+    let mut new_embeddings = new_embeddings.iter();
+    let mut result = Vec::new();
+
+    for op in ops {
+        let MemoryOperation::Add { fact } = op else {
+            result.push(op);
+            continue;
+        };
+        let Some(embedding) = new_embeddings.next() else {
+            result.push(MemoryOperation::Add { fact });
+            continue;
+        };
+
+        if accepted.iter().any(|e| cosine_similarity(e, embedding) >= threshold) {
+            continue;
+        }
+        match nearest(embedding, existing) {
+            Some((_, similarity)) if similarity >= DUPLICATE_SIMILARITY => {}
+            Some((memory, similarity)) if similarity >= threshold => {
+                if touched.insert(memory.id.clone()) {
+                    result.push(MemoryOperation::Update {
+                        id: memory.id.clone(),
+                        fact: merge_attributes(memory, fact),
+                    });
+                }
+            }
+            _ => {
+                accepted.push(embedding);
+                result.push(MemoryOperation::Add { fact });
+            }
+        }
+    }
+    result
+}
+
+/// Group items whose similarity is at least `threshold` (single linkage).
+/// Only groups with two or more members are returned.
+fn cluster(embeddings: &[Vec<f32>], threshold: f32) -> Vec<Vec<usize>> {
+    let mut parent: Vec<usize> = (0..embeddings.len()).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for i in 0..embeddings.len() {
+        for j in (i + 1)..embeddings.len() {
+            if cosine_similarity(&embeddings[i], &embeddings[j]) >= threshold {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                parent[b] = a;
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of_root = std::collections::HashMap::new();
+    for i in 0..embeddings.len() {
+        let root = find(&mut parent, i);
+        let index = *group_of_root.entry(root).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[index].push(i);
+    }
+    groups.retain(|g| g.len() > 1);
+    groups
+}
+
+/// Cluster stored memories and propose merging each cluster into its most detailed fact
+pub async fn consolidate_memories(storage: &StorageEngine) -> Result<Vec<MemoryMergeProposal>, String> {
+    let config = load_config();
+    let memories = memory_embeddings(storage, &config.embedding_model).await?;
+    let embeddings: Vec<Vec<f32>> = memories.iter().map(|(_, _, e)| e.clone()).collect();
+
+    Ok(cluster(&embeddings, config.memory_similarity_threshold)
+        .into_iter()
+        .map(|group| {
+            let contents: Vec<String> = group.iter().map(|&i| memories[i].1.clone()).collect();
+            let merged_content = contents.iter().max_by_key(|c| c.len()).cloned().unwrap_or_default();
+            MemoryMergeProposal {
+                memory_ids: group.iter().map(|&i| memories[i].0.clone()).collect(),
+                contents,
+                merged_content,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(content: &str) -> MemoryOperation {
+        MemoryOperation::Add { fact: MemoryFact::new(content) }
+    }
+
+    fn memory(id: &str, content: &str, category: MemoryCategory, importance: u8) -> Memory {
+        Memory {
+            id: id.to_string(),
+            content: content.to_string(),
+            created_at: String::new(),
+            category,
+            importance,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_deduplicate_merges_and_rejects() {
+        let existing = vec![
+            (memory("engineer", "Is a software engineer", MemoryCategory::Work, 4), vec![1.0, 0.0, 0.0]),
+            (memory("berlin", "Lives in Berlin", MemoryCategory::Other, 3), vec![0.0, 1.0, 0.0]),
+        ];
+        let ops = vec![
+            add("Works as a software developer"),
+            add("Lives in Berlin"),
+            add("Has a cat"),
+            add("Owns a cat"),
+        ];
+        let new_embeddings = vec![
+            vec![0.9, 0.35, 0.0], // close to "engineer" -> merge
+            vec![0.0, 1.0, 0.0],  // identical to "berlin" -> reject
+            vec![0.0, 0.0, 1.0],  // new
+            vec![0.0, 0.1, 0.99], // same as the previous new fact -> reject
+        ];
+
+        let result = deduplicate_operations(ops, &new_embeddings, &existing, 0.9);
+        assert_eq!(
+            result,
+            vec![
+                // The merge keeps the stored memory's category and higher importance
+                MemoryOperation::Update {
+                    id: "engineer".to_string(),
+                    fact: MemoryFact {
+                        category: MemoryCategory::Work,
+                        importance: 4,
+                        ..MemoryFact::new("Works as a software developer")
+                    },
+                },
+                add("Has a cat"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deduplicate_single_fact() {
+        let existing = vec![(memory("tea", "Likes tea", MemoryCategory::Preferences, 2), vec![1.0, 0.0])];
+        let fact = |content: &str, importance: u8| MemoryFact { importance, ..MemoryFact::new(content) };
+
+        assert_eq!(
+            deduplicate_single(fact("Likes tea", 2), &[1.0, 0.0], &existing, 0.9),
+            FactDedup::Duplicate { id: "tea".to_string() }
+        );
+        // Same fact, but the user marked it more important
+        assert_eq!(
+            deduplicate_single(fact("Likes tea", 5), &[1.0, 0.0], &existing, 0.9),
+            FactDedup::Merge {
+                id: "tea".to_string(),
+                fact: MemoryFact { category: MemoryCategory::Preferences, ..fact("Likes tea", 5) },
+            }
+        );
+        assert_eq!(
+            deduplicate_single(fact("Enjoys green tea", 1), &[0.9, 0.35], &existing, 0.9),
+            FactDedup::Merge {
+                id: "tea".to_string(),
+                fact: MemoryFact { category: MemoryCategory::Preferences, ..fact("Enjoys green tea", 2) },
+            }
+        );
+        assert_eq!(
+            deduplicate_single(fact("Has a dog", 3), &[0.0, 1.0], &existing, 0.9),
+            FactDedup::New(fact("Has a dog", 3))
+        );
+    }
+
+    #[test]
+    fn test_cluster_groups_similar() {
+        let embeddings = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![0.99, 0.05], vec![0.1, 0.0]];
+        assert_eq!(cluster(&embeddings, 0.95), vec![vec![0, 2, 3]]);
+    }
+}
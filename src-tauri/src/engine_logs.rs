@@ -0,0 +1,287 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Emitter};
+
+use crate::config::get_data_dir;
+
+/// Lines kept in memory for `get_engine_logs`
+const BUFFER_CAPACITY: usize = 2000;
+/// Size at which `engine.log` is rotated
+const MAX_LOG_FILE_BYTES: u64 = 5 * 1024 * 1024;
+/// Rotated files kept next to `engine.log` (`engine.log.1` is the newest)
+const ROTATED_LOG_FILES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    /// Level of one line of engine output. Ollama's own logs carry `level=INFO`;
+    /// llama.cpp and Go runtime output don't, so those are classified by keywords.
+    pub fn parse_line(line: &str) -> Self {
+        if let Some(rest) = line.split_whitespace().find_map(|field| field.strip_prefix("level=")) {
+            match rest.trim_matches('"').to_ascii_uppercase().as_str() {
+                "DEBUG" | "TRACE" => return LogLevel::Debug,
+                "WARN" | "WARNING" => return LogLevel::Warn,
+                "ERROR" => return LogLevel::Error,
+                "INFO" => return LogLevel::Info,
+                _ => {}
+            }
+        }
+        let lower = line.to_ascii_lowercase();
+        if ["error", "panic", "fatal", "failed"].iter().any(|k| lower.contains(k)) {
+            LogLevel::Error
+        } else if lower.contains("warn") {
+            LogLevel::Warn
+        } else {
+            LogLevel::Info
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineLogLine {
+    /// Increases by one per line; pass the last one seen as `since` to get only newer lines
+    pub seq: u64,
+    pub timestamp: String,
+    pub level: LogLevel,
+    /// "stdout" or "stderr"
+    pub stream: String,
+    pub message: String,
+}
+
+/// The most recent engine output, oldest first
+struct LogBuffer {
+    lines: VecDeque<EngineLogLine>,
+    next_seq: u64,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(capacity),
+            next_seq: 1,
+            capacity,
+        }
+    }
+
+    fn push(&mut self, stream: &str, message: &str) -> EngineLogLine {
+        let line = EngineLogLine {
+            seq: self.next_seq,
+            timestamp: Utc::now().to_rfc3339(),
+            level: LogLevel::parse_line(message),
+            stream: stream.to_string(),
+            message: message.to_string(),
+        };
+        self.next_seq += 1;
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line.clone());
+        line
+    }
+
+    fn query(&self, since: Option<u64>, level: Option<LogLevel>) -> Vec<EngineLogLine> {
+        self.lines
+            .iter()
+            .filter(|l| since.is_none_or(|s| l.seq > s))
+            .filter(|l| level.is_none_or(|min| l.level >= min))
+            .cloned()
+            .collect()
+    }
+}
+
+/// `engine.log` plus up to `keep` rotated copies, rotated once it grows past `max_bytes`
+struct LogFile {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: Option<File>,
+}
+
+impl LogFile {
+    fn new(path: PathBuf, max_bytes: u64, keep: usize) -> Self {
+        Self {
+            path,
+            max_bytes,
+            keep,
+            file: None,
+        }
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+        let _ = fs::remove_file(self.rotated(self.keep));
+        for n in (1..self.keep).rev() {
+            let from = self.rotated(n);
+            if from.exists() {
+                fs::rename(&from, self.rotated(n + 1))?;
+            }
+        }
+        if self.keep > 0 {
+            fs::rename(&self.path, self.rotated(1))
+        } else {
+            fs::remove_file(&self.path)
+        }
+    }
+
+    fn write_line(&mut self, line: &EngineLogLine) -> std::io::Result<()> {
+        let size = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        if size >= self.max_bytes {
+            self.rotate()?;
+        }
+        if self.file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            self.file = Some(OpenOptions::new().create(true).append(true).open(&self.path)?);
+        }
+        let file = self.file.as_mut().expect("log file was just opened");
+        writeln!(file, "{} [{}] {}", line.timestamp, line.stream, line.message)
+    }
+}
+
+struct EngineLogs {
+    buffer: LogBuffer,
+    file: LogFile,
+}
+
+static ENGINE_LOGS: OnceLock<Mutex<EngineLogs>> = OnceLock::new();
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+fn engine_logs() -> &'static Mutex<EngineLogs> {
+    ENGINE_LOGS.get_or_init(|| {
+        Mutex::new(EngineLogs {
+            buffer: LogBuffer::new(BUFFER_CAPACITY),
+            file: LogFile::new(log_path(), MAX_LOG_FILE_BYTES, ROTATED_LOG_FILES),
+        })
+    })
+}
+
+/// `~/.openworld/logs/engine.log`
+pub fn log_path() -> PathBuf {
+    get_data_dir().join("logs").join("engine.log")
+}
+
+/// Stream new lines to the frontend as `engine-log` events
+pub fn init(app: AppHandle) {
+    let _ = APP.set(app);
+}
+
+/// Store one line of engine output and forward it to the frontend
+pub fn record(stream: &str, message: &str) {
+    let message = message.trim_end();
+    if message.is_empty() {
+        return;
+    }
+    let line = {
+        let Ok(mut logs) = engine_logs().lock() else { return };
+        let line = logs.buffer.push(stream, message);
+        if let Err(e) = logs.file.write_line(&line) {
+            eprintln!("[openworld] Failed to write engine log: {}", e);
+        }
+        line
+    };
+    if let Some(app) = APP.get() {
+        let _ = app.emit("engine-log", &line);
+    }
+}
+
+/// Read `reader` line by line on its own thread, recording each line under `stream`.
+/// The pipe is drained until EOF: if it stopped being read, the engine would block on
+/// its next write once the pipe buffer is full.
+pub fn capture(reader: impl Read + Send + 'static, stream: &'static str) {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) => break,
+                Ok(_) => {
+                    // llama.cpp output isn't always valid UTF-8
+                    let line = String::from_utf8_lossy(&buf);
+                    // Keep the terminal output developers are used to
+                    eprintln!("[ollama] {}", line.trim_end());
+                    record(stream, &line);
+                }
+                Err(_) => break,
+            }
+        }
+    });
+}
+
+/// Buffered lines newer than `since` (a `seq`) at `level` or above
+pub fn get_logs(since: Option<u64>, level: Option<LogLevel>) -> Vec<EngineLogLine> {
+    engine_logs()
+        .lock()
+        .map(|logs| logs.buffer.query(since, level))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_levels() {
+        let cases = [
+            ("time=2025-01-01T00:00:00Z level=INFO source=server.go:1 msg=\"listening\"", LogLevel::Info),
+            ("time=2025-01-01T00:00:00Z level=WARN source=gpu.go:1 msg=\"no compatible GPUs\"", LogLevel::Warn),
+            ("time=2025-01-01T00:00:00Z level=ERROR source=sched.go:1 msg=\"error loading llama server\"", LogLevel::Error),
+            ("time=2025-01-01T00:00:00Z level=DEBUG msg=x", LogLevel::Debug),
+            ("llama_model_load: error loading model: unable to allocate backend buffer", LogLevel::Error),
+            ("[GIN] 2025/01/01 - 00:00:00 | 200 |  1.2ms | 127.0.0.1 | GET \"/api/tags\"", LogLevel::Info),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(LogLevel::parse_line(line), expected, "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_buffer_drops_oldest_and_filters() {
+        let mut buffer = LogBuffer::new(3);
+        buffer.push("stderr", "level=INFO msg=one");
+        buffer.push("stderr", "level=ERROR msg=two");
+        buffer.push("stderr", "level=INFO msg=three");
+        buffer.push("stdout", "level=WARN msg=four");
+
+        let all = buffer.query(None, None);
+        assert_eq!(all.iter().map(|l| l.seq).collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(buffer.query(Some(3), None).len(), 1);
+        let warnings = buffer.query(None, Some(LogLevel::Warn));
+        assert_eq!(warnings.iter().map(|l| l.seq).collect::<Vec<_>>(), vec![2, 4]);
+    }
+
+    #[test]
+    fn test_log_file_rotates() {
+        let dir = std::env::temp_dir().join(format!("openworld-test-{}", uuid::Uuid::new_v4()));
+        let mut file = LogFile::new(dir.join("engine.log"), 64, 2);
+        let mut buffer = LogBuffer::new(10);
+        for i in 0..8 {
+            file.write_line(&buffer.push("stderr", &format!("line {} with some padding", i))).unwrap();
+        }
+        assert!(file.rotated(1).exists());
+        assert!(file.rotated(2).exists());
+        assert!(!file.rotated(3).exists());
+        assert!(fs::read_to_string(dir.join("engine.log")).unwrap().contains("line 7"));
+        let _ = fs::remove_dir_all(dir);
+    }
+}
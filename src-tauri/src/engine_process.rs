@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, Signal, System, UpdateKind};
+
+use crate::config::get_data_dir;
+
+/// How long the engine gets to shut down after SIGTERM before it is killed
+pub const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+const STOP_POLL_MS: u64 = 100;
+
+/// Who is serving `ollama_host`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EngineOwner {
+    /// The engine process this app started
+    Managed,
+    /// Some other Ollama (system install, another app) answering on the same host
+    External,
+    /// Nothing is answering
+    None,
+}
+
+/// The engine we started, recorded in `engine.pid` so the next launch can find it if the
+/// app died without stopping it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PidFile {
+    pub pid: u32,
+    pub binary_path: String,
+    /// Process start time (seconds since the epoch), so a recycled pid isn't mistaken for ours
+    pub started_at: u64,
+}
+
+fn pidfile_path(root: &Path) -> PathBuf {
+    root.join("engine.pid")
+}
+
+fn read_pidfile(root: &Path) -> Option<PidFile> {
+    let content = fs::read_to_string(pidfile_path(root)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn process_info(system: &mut System, pid: u32) -> Option<(Option<PathBuf>, u64)> {
+    let pid = Pid::from_u32(pid);
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing().with_exe(UpdateKind::OnlyIfNotSet),
+    );
+    let process = system.process(pid)?;
+    Some((process.exe().map(Path::to_path_buf), process.start_time()))
+}
+
+/// Record a freshly spawned engine process
+pub fn write_pidfile(pid: u32, binary_path: &str) -> Result<(), String> {
+    let started_at = process_info(&mut System::new(), pid).map(|(_, t)| t).unwrap_or(0);
+    let record = PidFile {
+        pid,
+        binary_path: binary_path.to_string(),
+        started_at,
+    };
+    let json = serde_json::to_string(&record).map_err(|e| format!("Failed to serialize pidfile: {}", e))?;
+    fs::write(pidfile_path(&get_data_dir()), json).map_err(|e| format!("Failed to write pidfile: {}", e))
+}
+
+pub fn remove_pidfile() {
+    let _ = fs::remove_file(pidfile_path(&get_data_dir()));
+}
+
+/// Whether the live process `observed` (exe, start time) is the one `record` describes
+fn is_recorded_engine(record: &PidFile, observed: &(Option<PathBuf>, u64)) -> bool {
+    let (exe, started_at) = observed;
+    if record.started_at != 0 && *started_at != record.started_at {
+        return false;
+    }
+    match exe {
+        Some(exe) => {
+            let recorded = Path::new(&record.binary_path);
+            exe == recorded || fs::canonicalize(recorded).is_ok_and(|r| &r == exe)
+        }
+        // Can't read the executable (permissions); the start time has to be enough
+        None => record.started_at != 0,
+    }
+}
+
+/// Send SIGTERM, then SIGKILL if the process is still around after `timeout`.
+/// Signals other than kill aren't supported on Windows, where this kills right away.
+fn terminate_pid(system: &mut System, pid: u32, timeout: Duration) {
+    let sys_pid = Pid::from_u32(pid);
+    let Some(process) = system.process(sys_pid) else { return };
+    if process.kill_with(Signal::Term) == Some(true) {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(STOP_POLL_MS));
+            if process_info(system, pid).is_none() {
+                return;
+            }
+        }
+        eprintln!("[openworld] Engine (pid {}) ignored SIGTERM for {}s, killing it", pid, timeout.as_secs());
+    }
+    if let Some(process) = system.process(sys_pid) {
+        process.kill();
+    }
+}
+
+/// Stop a child we spawned: SIGTERM first so the engine can unload models cleanly,
+/// SIGKILL after `timeout`. The child is reaped either way.
+pub fn terminate_child(child: &mut Child, timeout: Duration) {
+    if let Ok(Some(_)) = child.try_wait() {
+        return;
+    }
+    let mut system = System::new();
+    let pid = child.id();
+    let graceful = process_info(&mut system, pid).is_some()
+        && system.process(Pid::from_u32(pid)).and_then(|p| p.kill_with(Signal::Term)) == Some(true);
+    if graceful {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = child.try_wait() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(STOP_POLL_MS));
+        }
+        eprintln!("[openworld] Engine (pid {}) ignored SIGTERM for {}s, killing it", pid, timeout.as_secs());
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Stop an engine left running by a previous app run that crashed or was killed.
+/// Only the process recorded in `engine.pid` is touched, and only if it still is that
+/// process. Returns whether an orphan was stopped.
+pub fn cleanup_orphan() -> bool {
+    let root = get_data_dir();
+    let Some(record) = read_pidfile(&root) else { return false };
+    let mut system = System::new();
+    let orphaned = process_info(&mut system, record.pid).is_some_and(|observed| is_recorded_engine(&record, &observed));
+    if orphaned {
+        eprintln!("[openworld] Stopping orphaned engine from a previous run (pid {})", record.pid);
+        terminate_pid(&mut system, record.pid, STOP_TIMEOUT);
+    }
+    let _ = fs::remove_file(pidfile_path(&root));
+    orphaned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorded_engine_matching() {
+        let record = PidFile {
+            pid: 42,
+            binary_path: "/home/me/.openworld/bin/ollama".to_string(),
+            started_at: 1_700_000_000,
+        };
+        let exe = Some(PathBuf::from("/home/me/.openworld/bin/ollama"));
+        assert!(is_recorded_engine(&record, &(exe.clone(), 1_700_000_000)));
+        // Same pid, later process: the pid was recycled
+        assert!(!is_recorded_engine(&record, &(exe, 1_700_000_500)));
+        // A system Ollama that happens to have the pid
+        assert!(!is_recorded_engine(&record, &(Some(PathBuf::from("/usr/bin/ollama")), 1_700_000_000)));
+        assert!(is_recorded_engine(&record, &(None, 1_700_000_000)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_child_sends_sigterm_first() {
+        use std::os::unix::process::ExitStatusExt;
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let started = Instant::now();
+        terminate_child(&mut child, Duration::from_secs(5));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(child.wait().unwrap().signal(), Some(15));
+    }
+}
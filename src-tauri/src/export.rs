@@ -0,0 +1,232 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::storage::{Conversation, ConversationFilter, Message, StorageEngine};
+
+pub const EXPORT_FORMAT_ID: &str = "openworld-export";
+pub const EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+}
+
+/// A conversation together with all of its (decrypted) messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedConversation {
+    #[serde(flatten)]
+    pub conversation: Conversation,
+    pub messages: Vec<Message>,
+}
+
+/// Top-level envelope of the lossless JSON export. Also read back by the importer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportDocument {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    pub conversations: Vec<ExportedConversation>,
+}
+
+fn load_conversation(storage: &StorageEngine, id: &str) -> Result<ExportedConversation, String> {
+    let conversation = storage
+        .get_conversation(id)?
+        .ok_or_else(|| format!("Conversation not found: {}", id))?;
+    let messages = storage.get_messages(id)?;
+    Ok(ExportedConversation { conversation, messages })
+}
+
+/// Export a single conversation in the requested format
+pub fn export_conversation(storage: &StorageEngine, id: &str, format: ExportFormat) -> Result<String, String> {
+    let convo = load_conversation(storage, id)?;
+    render(&[convo], format)
+}
+
+/// Export every conversation into a single document in the requested format
+pub fn export_all_conversations(storage: &StorageEngine, format: ExportFormat) -> Result<String, String> {
+    let convos = storage
+        .list_conversations(&ConversationFilter::default())?
+        .iter()
+        .map(|c| load_conversation(storage, &c.id))
+        .collect::<Result<Vec<_>, _>>()?;
+    render(&convos, format)
+}
+
+fn render(convos: &[ExportedConversation], format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Markdown => Ok(render_markdown(convos)),
+        ExportFormat::Json => render_json(convos),
+        ExportFormat::Html => Ok(render_html(convos)),
+    }
+}
+
+fn role_label(role: &str) -> &str {
+    match role {
+        "user" => "User",
+        "assistant" => "Assistant",
+        "system" => "System",
+        other => other,
+    }
+}
+
+fn render_markdown(convos: &[ExportedConversation]) -> String {
+    let mut out = String::new();
+    for (i, convo) in convos.iter().enumerate() {
+        if i > 0 {
+            out.push_str("\n---\n\n");
+        }
+        let c = &convo.conversation;
+        out.push_str(&format!("# {}\n\n", c.title));
+        out.push_str(&format!("- **Model:** {}\n", c.model));
+        out.push_str(&format!("- **Created:** {}\n", c.created_at));
+        out.push_str(&format!("- **Updated:** {}\n\n", c.updated_at));
+        for msg in &convo.messages {
+            out.push_str(&format!("## {}\n\n", role_label(&msg.role)));
+            out.push_str(&format!("_{}_\n\n", msg.timestamp));
+            out.push_str(msg.content.trim_end());
+            out.push_str("\n\n");
+        }
+    }
+    out
+}
+
+fn render_json(convos: &[ExportedConversation]) -> Result<String, String> {
+    let doc = ExportDocument {
+        format: EXPORT_FORMAT_ID.to_string(),
+        version: EXPORT_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        conversations: convos.to_vec(),
+    };
+    serde_json::to_string_pretty(&doc).map_err(|e| format!("Failed to serialize export: {}", e))
+}
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+const HTML_STYLE: &str = "body{font-family:-apple-system,BlinkMacSystemFont,'Segoe UI',Roboto,sans-serif;max-width:860px;margin:2rem auto;padding:0 1rem;color:#1f2328;background:#fff}\
+h1{font-size:1.6rem;margin-bottom:.25rem}\
+.meta{color:#656d76;font-size:.85rem;margin-bottom:1.5rem}\
+.msg{border:1px solid #d0d7de;border-radius:8px;padding:.75rem 1rem;margin:.75rem 0}\
+.msg.user{background:#f6f8fa}\
+.role{font-weight:600;font-size:.9rem}\
+.time{color:#656d76;font-size:.75rem;margin-left:.5rem}\
+.content{white-space:pre-wrap;word-wrap:break-word;margin-top:.5rem;line-height:1.5}\
+hr{border:none;border-top:1px solid #d0d7de;margin:2.5rem 0}";
+
+fn render_html(convos: &[ExportedConversation]) -> String {
+    let page_title = match convos {
+        [single] => single.conversation.title.clone(),
+        _ => "OpenWorld conversations".to_string(),
+    };
+
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>{}</title>\n", escape_html(&page_title)));
+    out.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", HTML_STYLE));
+
+    for (i, convo) in convos.iter().enumerate() {
+        if i > 0 {
+            out.push_str("<hr>\n");
+        }
+        let c = &convo.conversation;
+        out.push_str(&format!("<h1>{}</h1>\n", escape_html(&c.title)));
+        out.push_str(&format!(
+            "<div class=\"meta\">Model: {} &middot; Created: {} &middot; Updated: {}</div>\n",
+            escape_html(&c.model),
+            escape_html(&c.created_at),
+            escape_html(&c.updated_at)
+        ));
+        for msg in &convo.messages {
+            out.push_str(&format!(
+                "<div class=\"msg {}\"><span class=\"role\">{}</span><span class=\"time\">{}</span><div class=\"content\">{}</div></div>\n",
+                escape_html(&msg.role),
+                escape_html(role_label(&msg.role)),
+                escape_html(&msg.timestamp),
+                escape_html(&msg.content)
+            ));
+        }
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> ExportedConversation {
+        ExportedConversation {
+            conversation: Conversation {
+                id: "c1".to_string(),
+                title: "Rust <tips>".to_string(),
+                created_at: "2024-01-01T00:00:00+00:00".to_string(),
+                updated_at: "2024-01-01T00:01:00+00:00".to_string(),
+                model: "llama3:8b".to_string(),
+                folder_id: None,
+                pinned: false,
+                archived: false,
+                deleted_at: None,
+                tags: Vec::new(),
+                memory_mode: Default::default(),
+                incognito: false,
+                persona_id: None,
+            },
+            messages: vec![
+                Message {
+                    id: "m1".to_string(),
+                    conversation_id: "c1".to_string(),
+                    role: "user".to_string(),
+                    content: "What is <Box>?".to_string(),
+                    timestamp: "2024-01-01T00:00:00+00:00".to_string(),
+                },
+                Message {
+                    id: "m2".to_string(),
+                    conversation_id: "c1".to_string(),
+                    role: "assistant".to_string(),
+                    content: "A heap pointer.".to_string(),
+                    timestamp: "2024-01-01T00:01:00+00:00".to_string(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_markdown_has_role_headings() {
+        let md = render_markdown(&[sample()]);
+        assert!(md.starts_with("# Rust <tips>\n"));
+        assert!(md.contains("## User\n"));
+        assert!(md.contains("## Assistant\n"));
+    }
+
+    #[test]
+    fn test_html_escapes_content() {
+        let html = render_html(&[sample()]);
+        assert!(html.contains("What is &lt;Box&gt;?"));
+        assert!(!html.contains("<Box>"));
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let json = render_json(&[sample()]).unwrap();
+        let doc: ExportDocument = serde_json::from_str(&json).unwrap();
+        assert_eq!(doc.format, EXPORT_FORMAT_ID);
+        assert_eq!(doc.conversations[0].conversation.id, "c1");
+        assert_eq!(doc.conversations[0].messages.len(), 2);
+    }
+}
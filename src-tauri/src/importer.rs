@@ -0,0 +1,427 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::export::{ExportDocument, EXPORT_FORMAT_ID};
+use crate::storage::{Conversation, StorageEngine};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportSource {
+    OpenWorld,
+    ChatGpt,
+    OpenWebUi,
+}
+
+impl ImportSource {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ImportSource::OpenWorld => "openworld",
+            ImportSource::ChatGpt => "chatgpt",
+            ImportSource::OpenWebUi => "open_webui",
+        }
+    }
+}
+
+/// Summary returned to the frontend after an import run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub source: ImportSource,
+    pub conversations_imported: usize,
+    pub messages_imported: usize,
+    pub skipped_duplicates: usize,
+    pub skipped_empty: usize,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct ParsedMessage {
+    role: String,
+    content: String,
+    timestamp: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+struct ParsedConversation {
+    external_id: String,
+    /// Keep this ID instead of generating a fresh one (our own exports)
+    keep_id: bool,
+    title: String,
+    model: String,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+    messages: Vec<ParsedMessage>,
+}
+
+/// Read an export file from disk and import every conversation it contains
+pub fn import_file(storage: &StorageEngine, path: &str) -> Result<ImportSummary, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read import file: {}", e))?;
+    import_json(storage, &content)
+}
+
+pub fn import_json(storage: &StorageEngine, content: &str) -> Result<ImportSummary, String> {
+    let value: Value = serde_json::from_str(content)
+        .map_err(|e| format!("Import file is not valid JSON: {}", e))?;
+    let (source, parsed) = parse(value)?;
+
+    let mut summary = ImportSummary {
+        source,
+        conversations_imported: 0,
+        messages_imported: 0,
+        skipped_duplicates: 0,
+        skipped_empty: 0,
+        errors: Vec::new(),
+    };
+
+    for convo in parsed {
+        if convo.messages.is_empty() {
+            summary.skipped_empty += 1;
+            continue;
+        }
+        let (conversation, messages) = to_storage(convo.clone());
+        match storage.import_conversation(source.as_str(), &convo.external_id, &conversation, &messages) {
+            Ok(true) => {
+                summary.conversations_imported += 1;
+                summary.messages_imported += messages.len();
+            }
+            Ok(false) => summary.skipped_duplicates += 1,
+            Err(e) => summary.errors.push(format!("{}: {}", conversation.title, e)),
+        }
+    }
+
+    eprintln!(
+        "[openworld] Import ({}): {} imported, {} duplicate(s), {} empty, {} error(s)",
+        source.as_str(),
+        summary.conversations_imported,
+        summary.skipped_duplicates,
+        summary.skipped_empty,
+        summary.errors.len()
+    );
+    Ok(summary)
+}
+
+fn parse(value: Value) -> Result<(ImportSource, Vec<ParsedConversation>), String> {
+    if value.get("format").and_then(Value::as_str) == Some(EXPORT_FORMAT_ID) {
+        return Ok((ImportSource::OpenWorld, parse_openworld(value)?));
+    }
+
+    let items = value
+        .as_array()
+        .ok_or_else(|| "Unrecognized import format".to_string())?;
+
+    // Both ChatGPT and Open WebUI export a top-level array; tell them apart by shape
+    let first = match items.first() {
+        Some(f) => f,
+        None => return Ok((ImportSource::ChatGpt, Vec::new())),
+    };
+    if first.get("mapping").is_some() {
+        Ok((ImportSource::ChatGpt, items.iter().filter_map(parse_chatgpt).collect()))
+    } else if first.get("chat").is_some() {
+        Ok((ImportSource::OpenWebUi, items.iter().filter_map(parse_open_webui).collect()))
+    } else {
+        Err("Unrecognized import format".to_string())
+    }
+}
+
+fn parse_openworld(value: Value) -> Result<Vec<ParsedConversation>, String> {
+    let doc: ExportDocument = serde_json::from_value(value)
+        .map_err(|e| format!("Invalid OpenWorld export: {}", e))?;
+
+    Ok(doc
+        .conversations
+        .into_iter()
+        .map(|c| ParsedConversation {
+            external_id: c.conversation.id,
+            keep_id: true,
+            title: c.conversation.title,
+            model: c.conversation.model,
+            created_at: parse_rfc3339(&c.conversation.created_at),
+            updated_at: parse_rfc3339(&c.conversation.updated_at),
+            messages: c
+                .messages
+                .into_iter()
+                .map(|m| ParsedMessage {
+                    role: m.role,
+                    timestamp: parse_rfc3339(&m.timestamp),
+                    content: m.content,
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+/// ChatGPT stores each conversation as a tree in `mapping`; the visible thread is
+/// the path from `current_node` back to the root.
+fn parse_chatgpt(item: &Value) -> Option<ParsedConversation> {
+    let mapping = item.get("mapping")?.as_object()?;
+    let external_id = item
+        .get("conversation_id")
+        .or_else(|| item.get("id"))
+        .and_then(Value::as_str)?
+        .to_string();
+
+    let mut messages = Vec::new();
+    let mut node_id = item.get("current_node").and_then(Value::as_str).map(str::to_string);
+    while let Some(id) = node_id {
+        let node = match mapping.get(&id) {
+            Some(n) => n,
+            None => break,
+        };
+        if let Some(msg) = node.get("message").filter(|m| !m.is_null()) {
+            let role = msg
+                .pointer("/author/role")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let content = chatgpt_content(msg.get("content"));
+            if matches!(role, "user" | "assistant" | "system") && !content.trim().is_empty() {
+                messages.push(ParsedMessage {
+                    role: role.to_string(),
+                    content,
+                    timestamp: msg.get("create_time").and_then(epoch_to_datetime),
+                });
+            }
+        }
+        node_id = node.get("parent").and_then(Value::as_str).map(str::to_string);
+    }
+    messages.reverse();
+
+    Some(ParsedConversation {
+        external_id,
+        keep_id: false,
+        title: item
+            .get("title")
+            .and_then(Value::as_str)
+            .unwrap_or("Imported conversation")
+            .to_string(),
+        model: item
+            .get("default_model_slug")
+            .and_then(Value::as_str)
+            .unwrap_or("chatgpt")
+            .to_string(),
+        created_at: item.get("create_time").and_then(epoch_to_datetime),
+        updated_at: item.get("update_time").and_then(epoch_to_datetime),
+        messages,
+    })
+}
+
+fn chatgpt_content(content: Option<&Value>) -> String {
+    let content = match content {
+        Some(c) => c,
+        None => return String::new(),
+    };
+    if let Some(parts) = content.get("parts").and_then(Value::as_array) {
+        // Non-string parts are attachments (images, files) which we can't carry over
+        parts
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        content
+            .get("text")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    }
+}
+
+/// Open WebUI keeps a message tree under `chat.history`, with a flat
+/// `chat.messages` list as fallback in older exports.
+fn parse_open_webui(item: &Value) -> Option<ParsedConversation> {
+    let chat = item.get("chat")?;
+    let external_id = item
+        .get("id")
+        .or_else(|| chat.get("id"))
+        .and_then(Value::as_str)?
+        .to_string();
+
+    let mut raw: Vec<&Value> = Vec::new();
+    if let Some(history) = chat.get("history").and_then(|h| h.get("messages")).and_then(Value::as_object) {
+        let mut node_id = chat
+            .pointer("/history/currentId")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        while let Some(id) = node_id {
+            let node = match history.get(&id) {
+                Some(n) => n,
+                None => break,
+            };
+            raw.push(node);
+            node_id = node.get("parentId").and_then(Value::as_str).map(str::to_string);
+        }
+        raw.reverse();
+    }
+    if raw.is_empty() {
+        if let Some(list) = chat.get("messages").and_then(Value::as_array) {
+            raw = list.iter().collect();
+        }
+    }
+
+    let messages: Vec<ParsedMessage> = raw
+        .into_iter()
+        .filter_map(|m| {
+            let role = m.get("role").and_then(Value::as_str)?;
+            let content = m.get("content").and_then(Value::as_str)?;
+            if !matches!(role, "user" | "assistant" | "system") || content.trim().is_empty() {
+                return None;
+            }
+            Some(ParsedMessage {
+                role: role.to_string(),
+                content: content.to_string(),
+                timestamp: m.get("timestamp").and_then(epoch_to_datetime),
+            })
+        })
+        .collect();
+
+    let model = chat
+        .get("models")
+        .and_then(Value::as_array)
+        .and_then(|models| models.first())
+        .and_then(Value::as_str)
+        .unwrap_or("open-webui")
+        .to_string();
+
+    Some(ParsedConversation {
+        external_id,
+        keep_id: false,
+        title: item
+            .get("title")
+            .or_else(|| chat.get("title"))
+            .and_then(Value::as_str)
+            .unwrap_or("Imported conversation")
+            .to_string(),
+        model,
+        created_at: item.get("created_at").and_then(epoch_to_datetime),
+        updated_at: item.get("updated_at").and_then(epoch_to_datetime),
+        messages,
+    })
+}
+
+fn parse_rfc3339(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s).ok().map(|d| d.with_timezone(&Utc))
+}
+
+/// Accepts seconds (int or float) or milliseconds since the epoch
+fn epoch_to_datetime(value: &Value) -> Option<DateTime<Utc>> {
+    let secs = value.as_f64()?;
+    let secs = if secs > 1e12 { secs / 1000.0 } else { secs };
+    DateTime::from_timestamp_millis((secs * 1000.0) as i64)
+}
+
+/// Build storage rows, filling missing timestamps and keeping them strictly
+/// increasing so `get_messages` (ordered by timestamp) preserves the thread order.
+fn to_storage(convo: ParsedConversation) -> (Conversation, Vec<(String, String, String)>) {
+    let first_msg_ts = convo.messages.iter().find_map(|m| m.timestamp);
+    let created_at = convo.created_at.or(first_msg_ts).unwrap_or_else(Utc::now);
+
+    let mut last = created_at - Duration::milliseconds(1);
+    let mut messages = Vec::with_capacity(convo.messages.len());
+    for msg in convo.messages {
+        let mut ts = msg.timestamp.unwrap_or(last);
+        if ts <= last {
+            ts = last + Duration::milliseconds(1);
+        }
+        last = ts;
+        messages.push((msg.role, msg.content, ts.to_rfc3339()));
+    }
+
+    let updated_at = convo.updated_at.map_or(last, |u| u.max(last));
+    let conversation = Conversation {
+        id: if convo.keep_id {
+            convo.external_id
+        } else {
+            Uuid::new_v4().to_string()
+        },
+        title: convo.title,
+        created_at: created_at.to_rfc3339(),
+        updated_at: updated_at.to_rfc3339(),
+        model: convo.model,
+        folder_id: None,
+        pinned: false,
+        archived: false,
+        deleted_at: None,
+        tags: Vec::new(),
+        memory_mode: Default::default(),
+        incognito: false,
+        persona_id: None,
+    };
+    (conversation, messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chatgpt_follows_current_branch() {
+        let json = serde_json::json!([{
+            "id": "abc",
+            "title": "Trip planning",
+            "create_time": 1700000000.5,
+            "update_time": 1700000100.0,
+            "current_node": "n3",
+            "mapping": {
+                "root": {"id": "root", "message": null, "parent": null, "children": ["n1"]},
+                "n1": {"id": "n1", "parent": "root", "children": ["n2", "n2b"], "message": {
+                    "author": {"role": "user"}, "create_time": 1700000001.0,
+                    "content": {"content_type": "text", "parts": ["Where should I go?"]}}},
+                "n2b": {"id": "n2b", "parent": "n1", "children": [], "message": {
+                    "author": {"role": "assistant"}, "create_time": 1700000002.0,
+                    "content": {"content_type": "text", "parts": ["Abandoned branch"]}}},
+                "n2": {"id": "n2", "parent": "n1", "children": ["n3"], "message": {
+                    "author": {"role": "assistant"}, "create_time": 1700000003.0,
+                    "content": {"content_type": "text", "parts": ["Try Lisbon."]}}},
+                "n3": {"id": "n3", "parent": "n2", "children": [], "message": {
+                    "author": {"role": "tool"}, "create_time": 1700000004.0,
+                    "content": {"content_type": "text", "parts": ["tool output"]}}}
+            }
+        }]);
+
+        let (source, convos) = parse(json).unwrap();
+        assert_eq!(source, ImportSource::ChatGpt);
+        let msgs: Vec<&str> = convos[0].messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(msgs, vec!["Where should I go?", "Try Lisbon."]);
+    }
+
+    #[test]
+    fn test_parse_open_webui_flat_messages() {
+        let json = serde_json::json!([{
+            "id": "w1",
+            "title": "Recipes",
+            "created_at": 1700000000,
+            "updated_at": 1700000050,
+            "chat": {
+                "models": ["llama3:8b"],
+                "messages": [
+                    {"id": "a", "role": "user", "content": "Pasta?", "timestamp": 1700000001},
+                    {"id": "b", "role": "assistant", "content": "Carbonara.", "timestamp": 1700000002}
+                ]
+            }
+        }]);
+
+        let (source, convos) = parse(json).unwrap();
+        assert_eq!(source, ImportSource::OpenWebUi);
+        assert_eq!(convos[0].model, "llama3:8b");
+        assert_eq!(convos[0].messages.len(), 2);
+    }
+
+    #[test]
+    fn test_to_storage_keeps_order_without_timestamps() {
+        let convo = ParsedConversation {
+            external_id: "x".to_string(),
+            keep_id: false,
+            title: "t".to_string(),
+            model: "m".to_string(),
+            created_at: None,
+            updated_at: None,
+            messages: vec![
+                ParsedMessage { role: "user".to_string(), content: "1".to_string(), timestamp: None },
+                ParsedMessage { role: "assistant".to_string(), content: "2".to_string(), timestamp: None },
+            ],
+        };
+        let (_, rows) = to_storage(convo);
+        assert!(rows[0].2 < rows[1].2);
+    }
+}
@@ -0,0 +1,156 @@
+use chrono::Utc;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::storage::{Conversation, MemoryMode, Message};
+
+struct IncognitoConversation {
+    conversation: Conversation,
+    messages: Vec<Message>,
+}
+
+/// Conversations that exist only for the lifetime of the process. Nothing here is
+/// ever written to `data.db`; quitting the app discards them.
+#[derive(Default)]
+pub struct IncognitoStore {
+    conversations: HashMap<String, IncognitoConversation>,
+}
+
+impl IncognitoStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.conversations.contains_key(id)
+    }
+
+    pub fn contains_message(&self, message_id: &str) -> bool {
+        self.conversations
+            .values()
+            .any(|c| c.messages.iter().any(|m| m.id == message_id))
+    }
+
+    pub fn create_conversation(&mut self, title: &str, model: &str, persona_id: Option<&str>) -> Conversation {
+        let now = Utc::now().to_rfc3339();
+        let conversation = Conversation {
+            id: Uuid::new_v4().to_string(),
+            title: title.to_string(),
+            created_at: now.clone(),
+            updated_at: now,
+            model: model.to_string(),
+            folder_id: None,
+            pinned: false,
+            archived: false,
+            deleted_at: None,
+            tags: Vec::new(),
+            // Incognito chats never read from or contribute to long-term memory
+            memory_mode: MemoryMode::Off,
+            incognito: true,
+            persona_id: persona_id.map(str::to_string),
+        };
+        self.conversations.insert(
+            conversation.id.clone(),
+            IncognitoConversation {
+                conversation: conversation.clone(),
+                messages: Vec::new(),
+            },
+        );
+        conversation
+    }
+
+    pub fn get_conversation(&self, id: &str) -> Option<Conversation> {
+        self.conversations.get(id).map(|c| c.conversation.clone())
+    }
+
+    /// Most recently updated first
+    pub fn list_conversations(&self) -> Vec<Conversation> {
+        let mut convos: Vec<Conversation> = self.conversations.values().map(|c| c.conversation.clone()).collect();
+        convos.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        convos
+    }
+
+    pub fn update_conversation_title(&mut self, id: &str, title: &str) -> Result<(), String> {
+        let convo = self.get_mut(id)?;
+        convo.conversation.title = title.to_string();
+        convo.conversation.updated_at = Utc::now().to_rfc3339();
+        Ok(())
+    }
+
+    pub fn delete_conversation(&mut self, id: &str) {
+        self.conversations.remove(id);
+    }
+
+    pub fn add_message(&mut self, conversation_id: &str, role: &str, content: &str) -> Result<Message, String> {
+        let convo = self.get_mut(conversation_id)?;
+        let now = Utc::now().to_rfc3339();
+        let message = Message {
+            id: Uuid::new_v4().to_string(),
+            conversation_id: conversation_id.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: now.clone(),
+        };
+        convo.messages.push(message.clone());
+        convo.conversation.updated_at = now;
+        Ok(message)
+    }
+
+    pub fn get_messages(&self, conversation_id: &str) -> Result<Vec<Message>, String> {
+        self.conversations
+            .get(conversation_id)
+            .map(|c| c.messages.clone())
+            .ok_or_else(|| format!("Conversation not found: {}", conversation_id))
+    }
+
+    /// Same semantics as `StorageEngine::edit_message`, except no edit history is kept.
+    /// Returns `Ok(None)` if the message doesn't belong to an incognito conversation.
+    pub fn edit_message(&mut self, message_id: &str, new_content: &str) -> Result<Option<Message>, String> {
+        let Some(convo) = self
+            .conversations
+            .values_mut()
+            .find(|c| c.messages.iter().any(|m| m.id == message_id))
+        else {
+            return Ok(None);
+        };
+        let index = convo.messages.iter().position(|m| m.id == message_id).unwrap_or_default();
+        if convo.messages[index].role != "user" {
+            return Err("Only user messages can be edited".to_string());
+        }
+        convo.messages.truncate(index + 1);
+        convo.messages[index].content = new_content.to_string();
+        convo.conversation.updated_at = Utc::now().to_rfc3339();
+        Ok(Some(convo.messages[index].clone()))
+    }
+
+    fn get_mut(&mut self, id: &str) -> Result<&mut IncognitoConversation, String> {
+        self.conversations
+            .get_mut(id)
+            .ok_or_else(|| format!("Conversation not found: {}", id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incognito_conversation_lifecycle() {
+        let mut store = IncognitoStore::new();
+        let convo = store.create_conversation("Helping a friend", "m", None);
+        assert!(convo.incognito);
+        assert_eq!(convo.memory_mode, MemoryMode::Off);
+
+        let question = store.add_message(&convo.id, "user", "First question").unwrap();
+        store.add_message(&convo.id, "assistant", "First answer").unwrap();
+        assert!(store.edit_message(&question.id, "Better question").unwrap().is_some());
+
+        let messages = store.get_messages(&convo.id).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "Better question");
+        assert!(store.edit_message("unknown", "x").unwrap().is_none());
+
+        store.delete_conversation(&convo.id);
+        assert!(!store.contains(&convo.id));
+    }
+}
@@ -0,0 +1,1015 @@
+use reqwest::Client;
+use ring::digest::{Context, SHA256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+
+/// Where Ollama publishes its release assets
+pub const RELEASES_BASE_URL: &str = "https://github.com/ollama/ollama/releases";
+/// Checksum manifest published with every release (`<sha256>  ./<asset>` per line)
+pub const CHECKSUMS_ASSET: &str = "sha256sum.txt";
+
+/// SHA-256 of release assets we have vetted ourselves, as `(version, asset, sha256)`.
+/// A published checksum that disagrees with an entry here is refused, so a
+/// compromised release page alone can't push a different binary. Releases without
+/// entries fall back to the published checksums only (logged at install time).
+///
+/// When the app starts shipping against (or pinning by default) a new Ollama release,
+/// add one entry per asset listed in `ollama::get_download_info`:
+/// 1. Download the assets and `sha256sum.txt` from the release page.
+/// 2. Run `sha256sum` on each asset yourself and check it against `sha256sum.txt`.
+/// 3. Cross-check the hashes with a second source (e.g. the release as fetched from
+///    another network or machine) before adding them here, lowercase, with the tag
+///    written as `vX.Y.Z`.
+const PINNED_CHECKSUMS: &[(&str, &str, &str)] = &[];
+
+/// Published checksums of one release
+#[derive(Debug, Clone, Default)]
+pub struct ReleaseChecksums {
+    /// Release tag (e.g. `v0.5.7`), when it could be read from the final download URL
+    pub version: Option<String>,
+    pub sha256: HashMap<String, String>,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parse a `sha256sum`-style manifest into `asset -> lowercase hex digest`
+pub fn parse_checksums(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let hash = parts.next()?;
+            let name = parts.next()?.trim_start_matches('*').trim_start_matches("./");
+            let valid = hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit());
+            valid.then(|| (name.to_string(), hash.to_lowercase()))
+        })
+        .collect()
+}
+
+/// The release tag in a URL like `.../releases/download/v0.5.7/ollama-darwin.tgz`
+fn release_version_from_url(url: &str) -> Option<String> {
+    let mut segments = url.split('/');
+    segments.find(|s| *s == "download")?;
+    segments.next().filter(|s| s.starts_with('v')).map(str::to_string)
+}
+
+fn pinned_checksum(pins: &[(&str, &str, &str)], version: &str, asset: &str) -> Option<String> {
+    pins.iter()
+        .find(|(v, a, _)| *v == version && *a == asset)
+        .map(|(_, _, hash)| hash.to_lowercase())
+}
+
+/// Download and parse a release's checksum manifest
+pub async fn fetch_checksums(client: &Client, url: &str) -> Result<ReleaseChecksums, String> {
+    let resp = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch release checksums: {}", e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Failed to fetch release checksums: HTTP {}", resp.status()));
+    }
+    let version = release_version_from_url(resp.url().as_str());
+    let text = resp
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read release checksums: {}", e))?;
+    Ok(ReleaseChecksums {
+        version,
+        sha256: parse_checksums(&text),
+    })
+}
+
+/// The hash an asset must have. For a version we have pinned, that is the pinned hash,
+/// and the published one must agree with it; otherwise it is the published hash.
+pub fn expected_checksum(checksums: &ReleaseChecksums, asset: &str) -> Result<String, String> {
+    expected_checksum_with(checksums, asset, PINNED_CHECKSUMS)
+}
+
+fn expected_checksum_with(
+    checksums: &ReleaseChecksums,
+    asset: &str,
+    pins: &[(&str, &str, &str)],
+) -> Result<String, String> {
+    let published = checksums.sha256.get(asset).cloned();
+    let version = checksums.version.as_deref().unwrap_or("(unknown version)");
+    match checksums.version.as_deref().and_then(|v| pinned_checksum(pins, v, asset)) {
+        Some(pinned) => {
+            if published.as_ref().is_some_and(|p| *p != pinned) {
+                return Err(format!(
+                    "Published checksum for {} {} does not match the pinned checksum; refusing to install",
+                    asset, version
+                ));
+            }
+            Ok(pinned)
+        }
+        None => {
+            eprintln!(
+                "[openworld] No pinned checksum for {} {}; trusting the published checksum",
+                asset, version
+            );
+            published.ok_or_else(|| format!("No published checksum for {}", asset))
+        }
+    }
+}
+
+/// Stream `url` into a temporary file inside `dir`, hashing as it goes. The file is
+/// deleted and an error returned unless its SHA-256 equals `expected_sha256`.
+/// `on_progress` receives `(downloaded, total)` bytes.
+pub async fn download_verified(
+    client: &Client,
+    url: &str,
+    expected_sha256: &str,
+    dir: &Path,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<PathBuf, String> {
+    let mut resp = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Download request failed: {}", e))?;
+    eprintln!("[openworld] Download response: HTTP {} from {}", resp.status(), resp.url());
+    if !resp.status().is_success() {
+        return Err(format!("Download failed with HTTP {}", resp.status()));
+    }
+    let total = resp.content_length();
+
+    let temp_path = dir.join(format!(".download-{}.part", Uuid::new_v4()));
+    let result = async {
+        let mut file = std::fs::File::create(&temp_path).map_err(|e| format!("Failed to create file: {}", e))?;
+        let mut hasher = Context::new(&SHA256);
+        let mut downloaded: u64 = 0;
+        while let Some(bytes) = resp.chunk().await.map_err(|e| format!("Download interrupted: {}", e))? {
+            file.write_all(&bytes).map_err(|e| format!("Write error: {}", e))?;
+            hasher.update(&bytes);
+            downloaded += bytes.len() as u64;
+            on_progress(downloaded, total);
+        }
+        file.sync_all().map_err(|e| format!("Write error: {}", e))?;
+
+        let actual = to_hex(hasher.finish().as_ref());
+        if !actual.eq_ignore_ascii_case(expected_sha256) {
+            return Err(format!(
+                "Checksum mismatch: expected {}, got {}. The download may be corrupted or tampered with.",
+                expected_sha256, actual
+            ));
+        }
+        eprintln!("[openworld] ✓ Verified SHA-256 {} ({} bytes)", actual, downloaded);
+        Ok(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => Ok(temp_path),
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    TarGz,
+    TarZst,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Recognize an archive by its magic bytes, so renamed or temporary files still work
+    pub fn detect(path: &Path) -> Result<Self, String> {
+        let mut magic = [0u8; 4];
+        let mut file = File::open(path).map_err(|e| format!("Failed to open archive: {}", e))?;
+        let read = file.read(&mut magic).map_err(|e| format!("Failed to read archive: {}", e))?;
+        match &magic[..read] {
+            [0x1f, 0x8b, ..] => Ok(ArchiveFormat::TarGz),
+            [0x28, 0xb5, 0x2f, 0xfd] => Ok(ArchiveFormat::TarZst),
+            [b'P', b'K', 0x03, 0x04] => Ok(ArchiveFormat::Zip),
+            _ => Err("Unsupported archive format (expected .tgz, .tar.zst or .zip)".to_string()),
+        }
+    }
+}
+
+/// Turn an archive entry name into a path relative to the extraction root. Absolute
+/// paths and `..` components are rejected so entries can't escape the destination.
+fn sanitize_entry_path(path: &Path) -> Option<PathBuf> {
+    let mut clean = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => clean.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!clean.as_os_str().is_empty()).then_some(clean)
+}
+
+/// A symlink created in `dir` may only point at something inside `root`. This is a lexical
+/// check; links are resolved for real once the whole archive is extracted.
+fn symlink_stays_inside(root: &Path, dir: &Path, target: &Path) -> bool {
+    if target.is_absolute() {
+        return false;
+    }
+    let mut resolved = dir.to_path_buf();
+    for component in target.components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return false;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+    resolved.starts_with(root)
+}
+
+/// Create (as needed) the directory `relative` under `root`, following symlinks extracted
+/// so far one component at a time, and return its canonical path. Fails as soon as a
+/// component resolves outside `root`, before anything is created there.
+fn resolve_dir(root: &Path, relative: &Path) -> Result<PathBuf, String> {
+    let mut dir = root.to_path_buf();
+    for component in relative.components() {
+        dir.push(component);
+        if fs::symlink_metadata(&dir).is_err() {
+            fs::create_dir(&dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+        dir = fs::canonicalize(&dir).map_err(|e| format!("Failed to resolve {}: {}", relative.display(), e))?;
+        if !dir.starts_with(root) {
+            return Err(format!("Archive entry escapes the install directory: {}", relative.display()));
+        }
+    }
+    Ok(dir)
+}
+
+/// Where the file or link `relative` goes: its resolved parent directory plus its name.
+/// An existing symlink at that spot is removed so nothing is ever written through it.
+fn entry_target(root: &Path, relative: &Path) -> Result<PathBuf, String> {
+    let parent = resolve_dir(root, relative.parent().unwrap_or(Path::new("")))?;
+    let name = relative.file_name().ok_or_else(|| format!("Invalid entry path: {}", relative.display()))?;
+    let target = parent.join(name);
+    if fs::symlink_metadata(&target).is_ok_and(|m| m.file_type().is_symlink()) {
+        fs::remove_file(&target).map_err(|e| format!("Failed to replace {}: {}", relative.display(), e))?;
+    }
+    Ok(target)
+}
+
+/// Unpack `archive` into `dest`, refusing any entry that would land outside it
+pub fn extract_archive(archive: &Path, dest: &Path) -> Result<(), String> {
+    fs::create_dir_all(dest).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let root = fs::canonicalize(dest).map_err(|e| format!("Failed to resolve {}: {}", dest.display(), e))?;
+    let file = File::open(archive).map_err(|e| format!("Failed to open archive: {}", e))?;
+    match ArchiveFormat::detect(archive)? {
+        ArchiveFormat::TarGz => extract_tar(flate2::read::GzDecoder::new(file), &root),
+        ArchiveFormat::TarZst => {
+            let decoder = zstd::stream::read::Decoder::new(file).map_err(|e| format!("Failed to read zstd archive: {}", e))?;
+            extract_tar(decoder, &root)
+        }
+        ArchiveFormat::Zip => extract_zip(file, &root),
+    }
+}
+
+/// `root` must be canonical
+fn extract_tar(reader: impl Read, root: &Path) -> Result<(), String> {
+    let mut archive = tar::Archive::new(reader);
+    let entries = archive.entries().map_err(|e| format!("Failed to read archive: {}", e))?;
+    let mut symlinks = Vec::new();
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read archive entry: {}", e))?;
+        let raw_path = entry.path().map_err(|e| format!("Invalid entry path: {}", e))?.into_owned();
+        let relative = match sanitize_entry_path(&raw_path) {
+            Some(p) => p,
+            None if raw_path.components().all(|c| c == Component::CurDir) => continue,
+            None => return Err(format!("Archive entry escapes the install directory: {}", raw_path.display())),
+        };
+
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            resolve_dir(root, &relative)?;
+        } else if entry_type.is_file() {
+            let target = entry_target(root, &relative)?;
+            let mode = entry.header().mode().unwrap_or(0o644);
+            let mut out = File::create(&target).map_err(|e| format!("Failed to extract {}: {}", relative.display(), e))?;
+            std::io::copy(&mut entry, &mut out).map_err(|e| format!("Failed to extract {}: {}", relative.display(), e))?;
+            set_mode(&target, mode)?;
+        } else if entry_type.is_symlink() {
+            let link = entry
+                .link_name()
+                .map_err(|e| format!("Invalid symlink: {}", e))?
+                .ok_or("Symlink without a target")?
+                .into_owned();
+            let target = entry_target(root, &relative)?;
+            if !symlink_stays_inside(root, target.parent().unwrap_or(root), &link) {
+                return Err(format!("Archive symlink escapes the install directory: {}", raw_path.display()));
+            }
+            create_symlink(&link, &target)?;
+            symlinks.push((target, raw_path));
+        }
+        // Hard links, devices and other special entries are not part of an engine release
+    }
+
+    // A link can look fine on its own and still escape through links created after it
+    // (e.g. `a -> .` followed by `b -> a/x/../..`), so check where each one really ends up
+    for (link, raw_path) in symlinks {
+        if fs::symlink_metadata(&link).is_err() {
+            continue;
+        }
+        if !fs::canonicalize(&link).is_ok_and(|resolved| resolved.starts_with(root)) {
+            return Err(format!("Archive symlink escapes the install directory: {}", raw_path.display()));
+        }
+    }
+    Ok(())
+}
+
+/// `root` must be canonical
+fn extract_zip(file: File, root: &Path) -> Result<(), String> {
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Failed to read zip archive: {}", e))?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| format!("Failed to read archive entry: {}", e))?;
+        let relative = entry
+            .enclosed_name()
+            .and_then(|p| sanitize_entry_path(&p))
+            .ok_or_else(|| format!("Archive entry escapes the install directory: {}", entry.name()))?;
+        if entry.is_dir() {
+            resolve_dir(root, &relative)?;
+            continue;
+        }
+        let target = entry_target(root, &relative)?;
+        let mut out = File::create(&target).map_err(|e| format!("Failed to extract {}: {}", relative.display(), e))?;
+        std::io::copy(&mut entry, &mut out).map_err(|e| format!("Failed to extract {}: {}", relative.display(), e))?;
+        if let Some(mode) = entry.unix_mode() {
+            set_mode(&target, mode)?;
+        }
+    }
+    Ok(())
+}
+
+fn set_mode(path: &Path, mode: u32) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))
+            .map_err(|e| format!("Permission error: {}", e))?;
+    }
+    #[cfg(not(unix))]
+    let _ = (path, mode);
+    Ok(())
+}
+
+fn create_symlink(link: &Path, target: &Path) -> Result<(), String> {
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(link, target).map_err(|e| format!("Failed to create symlink: {}", e))?;
+    #[cfg(not(unix))]
+    let _ = (link, target);
+    Ok(())
+}
+
+/// Replace `dest` with `src` using renames, keeping the old copy until the new one is in place
+fn replace_path(src: &Path, dest: &Path) -> Result<(), String> {
+    let backup = dest.with_file_name(format!(
+        ".{}.old-{}",
+        dest.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        Uuid::new_v4()
+    ));
+    let had_previous = fs::symlink_metadata(dest).is_ok();
+    if had_previous {
+        fs::rename(dest, &backup).map_err(|e| format!("Failed to move {} aside: {}", dest.display(), e))?;
+    }
+    if let Err(e) = fs::rename(src, dest) {
+        if had_previous {
+            let _ = fs::rename(&backup, dest);
+        }
+        return Err(format!("Failed to install {}: {}", dest.display(), e));
+    }
+    if had_previous {
+        if backup.is_dir() {
+            let _ = fs::remove_dir_all(&backup);
+        } else {
+            let _ = fs::remove_file(&backup);
+        }
+    }
+    Ok(())
+}
+
+/// Move an extracted release into place. Ollama ships either `bin/ollama` + `lib/ollama/…`
+/// (Linux, Windows) or the binary and its libraries at the top level (macOS). The `lib/`
+/// tree goes next to `bin_dir` so the engine finds it at `<exe dir>/../lib/ollama`.
+fn install_extracted(staging: &Path, bin_dir: &Path, binary_name: &str) -> Result<PathBuf, String> {
+    let (source_bin_dir, binary) = [staging.join("bin"), staging.to_path_buf()]
+        .into_iter()
+        .map(|dir| {
+            let binary = dir.join(binary_name);
+            (dir, binary)
+        })
+        .find(|(_, binary)| binary.is_file())
+        .ok_or("Extraction succeeded but Ollama binary not found in archive")?;
+
+    let staged_lib = staging.join("lib");
+    if staged_lib.is_dir() {
+        let install_root = bin_dir.parent().ok_or("Install directory has no parent")?;
+        replace_path(&staged_lib, &install_root.join("lib"))?;
+    }
+
+    // Libraries shipped next to the binary stay next to it; the binary goes last
+    let entries = fs::read_dir(&source_bin_dir).map_err(|e| format!("Failed to read extracted files: {}", e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        if path == binary || (source_bin_dir == staging && (name == "lib" || name == "bin")) {
+            continue;
+        }
+        replace_path(&path, &bin_dir.join(&name))?;
+    }
+    make_executable(&binary)?;
+    let bin_path = bin_dir.join(binary_name);
+    replace_path(&binary, &bin_path)?;
+    Ok(bin_path)
+}
+
+/// Install a verified download into `bin_dir` as `binary_name`. Archives are unpacked
+/// into a staging directory first and files are then moved into place with renames,
+/// so a failed install never leaves a half-written binary behind.
+pub fn install_download(download: &Path, needs_extract: bool, bin_dir: &Path, binary_name: &str) -> Result<PathBuf, String> {
+    let bin_path = bin_dir.join(binary_name);
+    if !needs_extract {
+        make_executable(download)?;
+        fs::rename(download, &bin_path).map_err(|e| format!("Failed to install binary: {}", e))?;
+        return Ok(bin_path);
+    }
+
+    let staging = bin_dir.join(format!(".staging-{}", Uuid::new_v4()));
+    fs::create_dir_all(&staging).map_err(|e| format!("Failed to create staging directory: {}", e))?;
+    eprintln!("[openworld] Extracting archive...");
+    let result = extract_archive(download, &staging).and_then(|()| install_extracted(&staging, bin_dir, binary_name));
+
+    let _ = fs::remove_dir_all(&staging);
+    let _ = fs::remove_file(download);
+    result
+}
+
+/// Directories that make up a bundled engine, relative to the install root
+const ENGINE_DIRS: &[&str] = &["bin", "lib"];
+/// Where the engine that was replaced by the last update is kept for rollback
+pub const PREVIOUS_ENGINE_DIR: &str = "engine-previous";
+
+/// Versions of the bundled engine, stored as `engine.json` in the install root
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EngineManifest {
+    /// Release tag of the engine in `bin/` (None if installed before versions were recorded)
+    pub current: Option<String>,
+    /// Release tag of the engine kept in `engine-previous/`, if any
+    pub previous: Option<String>,
+    pub installed_at: Option<String>,
+}
+
+fn manifest_path(root: &Path) -> PathBuf {
+    root.join("engine.json")
+}
+
+pub fn load_manifest(root: &Path) -> EngineManifest {
+    fs::read_to_string(manifest_path(root))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_manifest(root: &Path, manifest: &EngineManifest) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(manifest).map_err(|e| format!("Failed to serialize engine manifest: {}", e))?;
+    fs::write(manifest_path(root), json).map_err(|e| format!("Failed to write engine manifest: {}", e))
+}
+
+/// Release tags always start with `v`; accept `0.5.7` as shorthand for `v0.5.7`
+pub fn normalize_version(version: &str) -> String {
+    let version = version.trim();
+    if version.starts_with('v') {
+        version.to_string()
+    } else {
+        format!("v{}", version)
+    }
+}
+
+/// Pull the version out of `ollama --version` output. When the CLI can reach a server it
+/// reports the server's version first (`ollama version is 0.5.7`) and adds a warning
+/// line if its own version differs, so the first match is the one that's running.
+/// Without a server only `Warning: client version is 0.5.7` is printed.
+pub fn parse_version_output(output: &str) -> Option<String> {
+    let find = |prefix: &str| {
+        output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(prefix).map(|v| v.trim().to_string()))
+    };
+    find("ollama version is ")
+        .or_else(|| find("Warning: client version is "))
+        .filter(|v| !v.is_empty())
+        .map(|v| normalize_version(&v))
+}
+
+fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Context::new(&SHA256);
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(to_hex(hasher.finish().as_ref()))
+}
+
+/// Copy a user-provided engine archive into `dir` so it can go through `install_download`
+/// without consuming the original. If a `sha256sum.txt` from the same release sits next
+/// to the archive and lists it, the archive must match it.
+pub fn prepare_local_archive(path: &Path, dir: &Path) -> Result<PathBuf, String> {
+    if !path.is_file() {
+        return Err(format!("Engine archive not found: {}", path.display()));
+    }
+    ArchiveFormat::detect(path)?;
+
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let checksums_path = path.with_file_name(CHECKSUMS_ASSET);
+    if let Ok(text) = fs::read_to_string(&checksums_path) {
+        if let Some(expected) = parse_checksums(&text).get(&name) {
+            let actual = sha256_file(path)?;
+            if &actual != expected {
+                return Err(format!(
+                    "Checksum mismatch for {}: expected {}, got {}. The archive may be corrupted or tampered with.",
+                    name, expected, actual
+                ));
+            }
+            eprintln!("[openworld] ✓ Verified SHA-256 {} against {}", actual, checksums_path.display());
+        }
+    }
+
+    let copy = dir.join(format!(".offline-{}.part", Uuid::new_v4()));
+    fs::copy(path, &copy).map_err(|e| format!("Failed to copy engine archive: {}", e))?;
+    Ok(copy)
+}
+
+/// The first engine archive placed in `dir` (e.g. `~/.openworld/offline/`), by file name
+pub fn find_offline_archive(dir: &Path) -> Option<PathBuf> {
+    let mut candidates: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            path.is_file() && !name.starts_with('.') && name != CHECKSUMS_ASSET
+        })
+        .collect();
+    candidates.sort();
+    candidates.into_iter().find(|path| ArchiveFormat::detect(path).is_ok())
+}
+
+/// Move the current engine into `engine-previous/`, replacing an older backup
+pub fn keep_previous(root: &Path) -> Result<(), String> {
+    let previous = root.join(PREVIOUS_ENGINE_DIR);
+    if previous.exists() {
+        fs::remove_dir_all(&previous).map_err(|e| format!("Failed to remove old engine backup: {}", e))?;
+    }
+    fs::create_dir_all(&previous).map_err(|e| format!("Failed to create engine backup: {}", e))?;
+    for dir in ENGINE_DIRS {
+        let current = root.join(dir);
+        if current.exists() {
+            fs::rename(&current, previous.join(dir)).map_err(|e| format!("Failed to back up engine {}: {}", dir, e))?;
+        }
+    }
+    let mut manifest = load_manifest(root);
+    manifest.previous = manifest.current.take();
+    save_manifest(root, &manifest)
+}
+
+/// Whether an engine replaced by an update is kept in `engine-previous/`
+pub fn has_previous(root: &Path) -> bool {
+    root.join(PREVIOUS_ENGINE_DIR).join("bin").is_dir()
+}
+
+/// Exchange the current engine with the one in `engine-previous/`
+pub fn swap_with_previous(root: &Path) -> Result<(), String> {
+    if !has_previous(root) {
+        return Err("No previous engine version to roll back to".to_string());
+    }
+    let previous = root.join(PREVIOUS_ENGINE_DIR);
+    let scratch = root.join(format!(".swap-{}", Uuid::new_v4()));
+    fs::create_dir_all(&scratch).map_err(|e| format!("Failed to prepare engine swap: {}", e))?;
+    for dir in ENGINE_DIRS {
+        let (current, backup, parked) = (root.join(dir), previous.join(dir), scratch.join(dir));
+        if current.exists() {
+            fs::rename(&current, &parked).map_err(|e| format!("Failed to swap engine {}: {}", dir, e))?;
+        }
+        if backup.exists() {
+            fs::rename(&backup, &current).map_err(|e| format!("Failed to swap engine {}: {}", dir, e))?;
+        }
+        if parked.exists() {
+            fs::rename(&parked, &backup).map_err(|e| format!("Failed to swap engine {}: {}", dir, e))?;
+        }
+    }
+    let _ = fs::remove_dir_all(&scratch);
+
+    let mut manifest = load_manifest(root);
+    std::mem::swap(&mut manifest.current, &mut manifest.previous);
+    manifest.installed_at = Some(chrono::Utc::now().to_rfc3339());
+    save_manifest(root, &manifest)
+}
+
+/// Delete the kept engine, e.g. after it was restored because an update failed
+pub fn discard_previous(root: &Path) -> Result<(), String> {
+    let previous = root.join(PREVIOUS_ENGINE_DIR);
+    if previous.exists() {
+        fs::remove_dir_all(&previous).map_err(|e| format!("Failed to remove engine backup: {}", e))?;
+    }
+    let mut manifest = load_manifest(root);
+    manifest.previous = None;
+    save_manifest(root, &manifest)
+}
+
+fn make_executable(path: &Path) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = std::fs::metadata(path)
+            .map_err(|e| format!("Metadata error: {}", e))?
+            .permissions();
+        perms.set_mode(0o755);
+        std::fs::set_permissions(path, perms).map_err(|e| format!("Permission error: {}", e))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    fn sha256_hex(data: &[u8]) -> String {
+        to_hex(ring::digest::digest(&SHA256, data).as_ref())
+    }
+
+    /// Minimal HTTP server standing in for the release host. Serves `routes` by path, 404 otherwise.
+    fn serve(routes: Vec<(&'static str, Vec<u8>)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                // Drain headers
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let path = request_line.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let (status, body) = match routes.iter().find(|(p, _)| *p == path) {
+                    Some((_, body)) => ("200 OK", body.clone()),
+                    None => ("404 Not Found", Vec::new()),
+                };
+                let header = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                let _ = stream.write_all(header.as_bytes());
+                let _ = stream.write_all(&body);
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("openworld-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_parse_checksums() {
+        let hash = "a".repeat(64);
+        let text = format!("{}  ./ollama-darwin.tgz\n{} *ollama-linux-amd64.tgz\nnot a checksum line\n", hash, hash.to_uppercase());
+        let sums = parse_checksums(&text);
+        assert_eq!(sums.len(), 2);
+        assert_eq!(sums["ollama-darwin.tgz"], hash);
+        assert_eq!(sums["ollama-linux-amd64.tgz"], hash);
+    }
+
+    #[test]
+    fn test_pinned_checksum_table_is_well_formed() {
+        for (version, asset, sha256) in PINNED_CHECKSUMS {
+            assert_eq!(normalize_version(version), *version, "{} {}", version, asset);
+            assert!(!asset.is_empty() && !asset.contains('/'), "asset must be a bare file name: {}", asset);
+            assert!(sha256.len() == 64 && sha256.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')), "{} {}", version, asset);
+        }
+    }
+
+    #[test]
+    fn test_pinned_checksum_must_match() {
+        let checksums = ReleaseChecksums {
+            version: Some("v1.2.3".to_string()),
+            sha256: [("ollama-darwin.tgz".to_string(), "b".repeat(64))].into_iter().collect(),
+        };
+        let good = "b".repeat(64);
+        let bad = "c".repeat(64);
+        assert!(expected_checksum_with(&checksums, "ollama-darwin.tgz", &[("v1.2.3", "ollama-darwin.tgz", &good)]).is_ok());
+        assert!(expected_checksum_with(&checksums, "ollama-darwin.tgz", &[("v1.2.3", "ollama-darwin.tgz", &bad)]).is_err());
+        assert!(expected_checksum_with(&checksums, "ollama-other.tgz", &[]).is_err());
+        assert_eq!(
+            release_version_from_url("https://github.com/ollama/ollama/releases/download/v1.2.3/sha256sum.txt"),
+            Some("v1.2.3".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_verified_accepts_good_and_rejects_tampered() {
+        let archive = b"pretend this is an ollama archive".to_vec();
+        let mut tampered = archive.clone();
+        tampered[0] ^= 0xff;
+        let manifest = format!("{}  ./ollama.tgz\n", sha256_hex(&archive));
+        let base = serve(vec![
+            ("/sha256sum.txt", manifest.into_bytes()),
+            ("/good/ollama.tgz", archive.clone()),
+            ("/bad/ollama.tgz", tampered),
+        ]);
+        let client = Client::new();
+        let dir = temp_dir();
+
+        let checksums = fetch_checksums(&client, &format!("{}/sha256sum.txt", base)).await.unwrap();
+        let expected = expected_checksum(&checksums, "ollama.tgz").unwrap();
+
+        let path = download_verified(&client, &format!("{}/good/ollama.tgz", base), &expected, &dir, |_, _| {})
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), archive);
+
+        let err = download_verified(&client, &format!("{}/bad/ollama.tgz", base), &expected, &dir, |_, _| {})
+            .await
+            .unwrap_err();
+        assert!(err.contains("Checksum mismatch"));
+        // Only the good download is left; the tampered one was removed
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_pinned_mismatch_rejected_even_if_published_matches() {
+        // A compromised release: the asset and its published checksum agree with each
+        // other, but not with the hash we pinned for that version
+        let archive = b"a replaced ollama archive".to_vec();
+        let manifest = format!("{}  ./ollama-darwin.tgz\n", sha256_hex(&archive));
+        let base = serve(vec![
+            ("/download/v1.2.3/sha256sum.txt", manifest.into_bytes()),
+            ("/download/v1.2.3/ollama-darwin.tgz", archive.clone()),
+        ]);
+        let client = Client::new();
+        let dir = temp_dir();
+
+        let checksums = fetch_checksums(&client, &format!("{}/download/v1.2.3/sha256sum.txt", base)).await.unwrap();
+        assert_eq!(checksums.version.as_deref(), Some("v1.2.3"));
+        let vetted = sha256_hex(b"the original ollama archive");
+        let pins = [("v1.2.3", "ollama-darwin.tgz", vetted.as_str())];
+        let err = expected_checksum_with(&checksums, "ollama-darwin.tgz", &pins).unwrap_err();
+        assert!(err.contains("does not match the pinned checksum"));
+
+        // Even with the published manifest missing the asset, the pinned hash decides
+        let unlisted = ReleaseChecksums { version: Some("v1.2.3".to_string()), sha256: HashMap::new() };
+        let expected = expected_checksum_with(&unlisted, "ollama-darwin.tgz", &pins).unwrap();
+        assert_eq!(expected, vetted);
+        let err = download_verified(&client, &format!("{}/download/v1.2.3/ollama-darwin.tgz", base), &expected, &dir, |_, _| {})
+            .await
+            .unwrap_err();
+        assert!(err.contains("Checksum mismatch"));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    fn tar_bytes(entries: &[(&str, &[u8], u32)], symlinks: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, data, mode) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(*mode);
+            header.set_entry_type(tar::EntryType::Regular);
+            builder.append_data(&mut header, path, *data).unwrap();
+        }
+        for (path, target) in symlinks {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(0);
+            header.set_entry_type(tar::EntryType::Symlink);
+            builder.append_link(&mut header, path, target).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    /// A tar whose single entry is named `name` verbatim (the builder refuses `..` paths)
+    fn raw_tar_entry(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_cksum();
+        let mut out = header.as_bytes().to_vec();
+        out.extend_from_slice(data);
+        out.resize(out.len().div_ceil(512) * 512 + 1024, 0);
+        out
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn write_fixture(dir: &Path, name: &str, data: &[u8]) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_install_linux_layout_from_tar_zst() {
+        let dir = temp_dir();
+        let bin_dir = dir.join("bin");
+        fs::create_dir_all(&bin_dir).unwrap();
+        let tar = tar_bytes(
+            &[("bin/ollama", b"new engine", 0o755), ("lib/ollama/libggml.so.1", b"lib", 0o644)],
+            &[("lib/ollama/libggml.so", "libggml.so.1")],
+        );
+        let archive = write_fixture(&dir, "download.part", &zstd::encode_all(&tar[..], 0).unwrap());
+        assert_eq!(ArchiveFormat::detect(&archive).unwrap(), ArchiveFormat::TarZst);
+
+        let bin_path = install_download(&archive, true, &bin_dir, "ollama").unwrap();
+        assert_eq!(fs::read(&bin_path).unwrap(), b"new engine");
+        assert_eq!(fs::read(dir.join("lib/ollama/libggml.so.1")).unwrap(), b"lib");
+        #[cfg(unix)]
+        assert_eq!(fs::read(dir.join("lib/ollama/libggml.so")).unwrap(), b"lib");
+        assert!(!archive.exists());
+        // No staging leftovers
+        assert_eq!(fs::read_dir(&bin_dir).unwrap().count(), 1);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_install_top_level_layout_from_tgz_and_zip() {
+        let dir = temp_dir();
+        let bin_dir = dir.join("bin");
+        fs::create_dir_all(&bin_dir).unwrap();
+        fs::write(bin_dir.join("ollama"), b"old engine").unwrap();
+
+        let tgz = gzip(&tar_bytes(&[("./ollama", b"darwin engine", 0o755), ("./libmlx.dylib", b"dylib", 0o644)], &[]));
+        let archive = write_fixture(&dir, "ollama-darwin.tgz", &tgz);
+        install_download(&archive, true, &bin_dir, "ollama").unwrap();
+        assert_eq!(fs::read(bin_dir.join("ollama")).unwrap(), b"darwin engine");
+        assert_eq!(fs::read(bin_dir.join("libmlx.dylib")).unwrap(), b"dylib");
+
+        let mut zip_writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        zip_writer.start_file("ollama.exe", options).unwrap();
+        zip_writer.write_all(b"windows engine").unwrap();
+        zip_writer.start_file("lib/ollama/ggml.dll", options).unwrap();
+        zip_writer.write_all(b"dll").unwrap();
+        let zip_data = zip_writer.finish().unwrap().into_inner();
+        let archive = write_fixture(&dir, "ollama-windows-amd64.zip", &zip_data);
+        install_download(&archive, true, &bin_dir, "ollama.exe").unwrap();
+        assert_eq!(fs::read(bin_dir.join("ollama.exe")).unwrap(), b"windows engine");
+        assert_eq!(fs::read(dir.join("lib/ollama/ggml.dll")).unwrap(), b"dll");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_extract_rejects_path_traversal() {
+        let dir = temp_dir();
+        let dest = dir.join("dest");
+        fs::create_dir_all(&dest).unwrap();
+
+        let archive = write_fixture(&dir, "evil.tgz", &gzip(&raw_tar_entry("../evil", b"pwned")));
+        assert!(extract_archive(&archive, &dest).unwrap_err().contains("escapes"));
+        assert!(!dir.join("evil").exists());
+
+        let symlink = gzip(&tar_bytes(&[], &[("bin/ollama", "../../../usr/bin/sh")]));
+        let archive = write_fixture(&dir, "evil-link.tgz", &symlink);
+        assert!(extract_archive(&archive, &dest).unwrap_err().contains("escapes"));
+
+        // Each link looks fine on its own, but `a/b/c` lands at `dest/c` and points above dest
+        let chained = gzip(&tar_bytes(&[], &[("a/b", ".."), ("a/b/c", ".."), ("a/b/c/ollama", "x")]));
+        let archive = write_fixture(&dir, "chained.tgz", &chained);
+        let chained_dest = dir.join("chained");
+        assert!(extract_archive(&archive, &chained_dest).unwrap_err().contains("escapes"));
+        assert!(!dir.join("ollama").exists());
+        // Lexically inside, but `s` is a link to `.`, so `s/x/../..` resolves above dest
+        let sneaky = gzip(&tar_bytes(&[("x/f", b"", 0o644)], &[("s", "."), ("l", "s/x/../..")]));
+        let archive = write_fixture(&dir, "sneaky.tgz", &sneaky);
+        assert!(extract_archive(&archive, &dir.join("sneaky")).unwrap_err().contains("escapes"));
+
+        let mut zip_writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        zip_writer.start_file("../evil.txt", zip::write::SimpleFileOptions::default()).unwrap();
+        zip_writer.write_all(b"pwned").unwrap();
+        let archive = write_fixture(&dir, "evil.zip", &zip_writer.finish().unwrap().into_inner());
+        assert!(extract_archive(&archive, &dest).unwrap_err().contains("escapes"));
+        assert!(!dir.join("evil.txt").exists());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_parse_version_output() {
+        assert_eq!(parse_version_output("ollama version is 0.5.7\n"), Some("v0.5.7".to_string()));
+        assert_eq!(
+            parse_version_output("ollama version is 0.6.0\nWarning: client version is 0.5.7\n"),
+            Some("v0.6.0".to_string())
+        );
+        assert_eq!(
+            parse_version_output("Warning: could not connect to a running Ollama instance\nWarning: client version is 0.5.7"),
+            Some("v0.5.7".to_string())
+        );
+        assert_eq!(parse_version_output("Error: unknown flag"), None);
+        assert_eq!(normalize_version(" v0.5.7 "), "v0.5.7");
+    }
+
+    #[test]
+    fn test_keep_previous_and_rollback() {
+        let root = temp_dir();
+        fs::create_dir_all(root.join("bin")).unwrap();
+        fs::create_dir_all(root.join("lib/ollama")).unwrap();
+        fs::write(root.join("bin/ollama"), b"v1").unwrap();
+        fs::write(root.join("lib/ollama/lib.so"), b"lib v1").unwrap();
+        save_manifest(&root, &EngineManifest { current: Some("v1".into()), ..Default::default() }).unwrap();
+
+        keep_previous(&root).unwrap();
+        assert!(!root.join("bin").exists());
+        fs::create_dir_all(root.join("bin")).unwrap();
+        fs::write(root.join("bin/ollama"), b"v2").unwrap();
+        let mut manifest = load_manifest(&root);
+        assert_eq!(manifest.previous.as_deref(), Some("v1"));
+        manifest.current = Some("v2".into());
+        save_manifest(&root, &manifest).unwrap();
+
+        swap_with_previous(&root).unwrap();
+        assert_eq!(fs::read(root.join("bin/ollama")).unwrap(), b"v1");
+        assert_eq!(fs::read(root.join("lib/ollama/lib.so")).unwrap(), b"lib v1");
+        assert_eq!(fs::read(root.join(PREVIOUS_ENGINE_DIR).join("bin/ollama")).unwrap(), b"v2");
+        // v2 shipped no lib/, so none is kept for it
+        assert!(!root.join(PREVIOUS_ENGINE_DIR).join("lib").exists());
+        let manifest = load_manifest(&root);
+        assert_eq!((manifest.current.as_deref(), manifest.previous.as_deref()), (Some("v1"), Some("v2")));
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_rollback_without_previous_keeps_current() {
+        let root = temp_dir();
+        fs::create_dir_all(root.join("bin")).unwrap();
+        fs::write(root.join("bin/ollama"), b"v1").unwrap();
+        let manifest = EngineManifest { current: Some("v1".into()), ..Default::default() };
+        save_manifest(&root, &manifest).unwrap();
+
+        assert!(!has_previous(&root));
+        assert!(swap_with_previous(&root).is_err());
+        assert_eq!(fs::read(root.join("bin/ollama")).unwrap(), b"v1");
+        assert_eq!(load_manifest(&root), manifest);
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_offline_archive_pickup_and_verification() {
+        let dir = temp_dir();
+        let offline = dir.join("offline");
+        fs::create_dir_all(&offline).unwrap();
+        assert_eq!(find_offline_archive(&offline), None);
+
+        let tgz = gzip(&tar_bytes(&[("ollama", b"engine", 0o755)], &[]));
+        fs::write(offline.join("README.txt"), b"drop the archive here").unwrap();
+        let archive = write_fixture(&offline, "ollama-darwin.tgz", &tgz);
+        assert_eq!(find_offline_archive(&offline), Some(archive.clone()));
+
+        // Without a manifest the archive is accepted as is, and the original is left alone
+        let copy = prepare_local_archive(&archive, &dir).unwrap();
+        assert_eq!(fs::read(&copy).unwrap(), tgz);
+        assert!(archive.exists());
+
+        fs::write(offline.join(CHECKSUMS_ASSET), format!("{}  ./ollama-darwin.tgz\n", "0".repeat(64))).unwrap();
+        assert!(prepare_local_archive(&archive, &dir).unwrap_err().contains("Checksum mismatch"));
+        fs::write(offline.join(CHECKSUMS_ASSET), format!("{}  ./ollama-darwin.tgz\n", sha256_hex(&tgz))).unwrap();
+        assert!(prepare_local_archive(&archive, &dir).is_ok());
+
+        assert!(prepare_local_archive(&offline.join("README.txt"), &dir).unwrap_err().contains("Unsupported"));
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,207 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::chat::{self, ChatMessage};
+use crate::config::AppConfig;
+use crate::embeddings;
+use crate::storage::{MemorySource, StorageEngine};
+use crate::AppState;
+
+/// Attempts per job before it is dropped
+const MAX_ATTEMPTS: u32 = 3;
+/// Delay before the first retry; doubles on each further attempt
+const RETRY_BASE_DELAY_SECS: u64 = 2;
+/// How often a paused queue checks whether chatting has finished
+const PAUSE_POLL_MS: u64 = 500;
+
+/// Number of chat replies currently streaming
+static ACTIVE_CHATS: AtomicUsize = AtomicUsize::new(0);
+
+/// Marks a chat reply as in progress for as long as it is alive
+pub struct ChatActivity;
+
+impl ChatActivity {
+    pub fn begin() -> Self {
+        ACTIVE_CHATS.fetch_add(1, Ordering::SeqCst);
+        ChatActivity
+    }
+}
+
+impl Drop for ChatActivity {
+    fn drop(&mut self) {
+        ACTIVE_CHATS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Background work triggered by a chat exchange. A job carries the storage and config
+/// of the profile it started in, so it still writes there if the user switches profiles.
+pub struct Job {
+    pub storage: Arc<StorageEngine>,
+    pub config: AppConfig,
+    pub task: Task,
+}
+
+/// `chat_model` is used when no utility model is configured
+#[derive(Debug)]
+pub enum Task {
+    GenerateTitle {
+        conversation_id: String,
+        messages: Vec<ChatMessage>,
+        chat_model: String,
+    },
+    ExtractFacts {
+        conversation_id: String,
+        messages: Vec<ChatMessage>,
+        chat_model: String,
+        existing_memories: Vec<(String, String)>,
+        pending_memories: Vec<String>,
+        source_message_ids: Vec<String>,
+    },
+}
+
+/// Handle for submitting jobs; they run one at a time in submission order
+pub struct JobQueue {
+    sender: UnboundedSender<Job>,
+}
+
+impl JobQueue {
+    pub fn enqueue(&self, job: Job) {
+        if self.sender.send(job).is_err() {
+            eprintln!("[openworld] Background job queue is closed, dropping job");
+        }
+    }
+}
+
+/// Start the worker and return the queue feeding it
+pub fn start(app: AppHandle) -> JobQueue {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tauri::async_runtime::spawn(worker(app, receiver));
+    JobQueue { sender }
+}
+
+fn retry_delay(attempt: u32) -> std::time::Duration {
+    std::time::Duration::from_secs(RETRY_BASE_DELAY_SECS << (attempt - 1))
+}
+
+async fn worker(app: AppHandle, mut receiver: UnboundedReceiver<Job>) {
+    while let Some(job) = receiver.recv().await {
+        for attempt in 1..=MAX_ATTEMPTS {
+            wait_while_chatting(job.config.pause_jobs_while_chatting).await;
+            match run_job(&app, &job).await {
+                Ok(()) => break,
+                Err(e) if attempt < MAX_ATTEMPTS => {
+                    let delay = retry_delay(attempt);
+                    eprintln!(
+                        "[openworld] Background job failed (attempt {}/{}), retrying in {}s: {}",
+                        attempt,
+                        MAX_ATTEMPTS,
+                        delay.as_secs(),
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => eprintln!("[openworld] Background job failed, giving up (non-fatal): {}", e),
+            }
+        }
+    }
+}
+
+/// Hold the queue while a reply is streaming so background calls don't compete with it.
+/// `pause` is the setting of the profile that queued the job.
+async fn wait_while_chatting(pause: bool) {
+    while pause && ACTIVE_CHATS.load(Ordering::SeqCst) > 0 {
+        tokio::time::sleep(tokio::time::Duration::from_millis(PAUSE_POLL_MS)).await;
+    }
+}
+
+async fn run_job(app: &AppHandle, job: &Job) -> Result<(), String> {
+    let utility_model = job.config.utility_model.clone().filter(|m| !m.trim().is_empty());
+    match &job.task {
+        Task::GenerateTitle {
+            conversation_id,
+            messages,
+            chat_model,
+        } => {
+            let model = utility_model.as_deref().unwrap_or(chat_model);
+            eprintln!("[openworld] Generating dynamic title with {}...", model);
+            let title = chat::generate_conversation_title(messages, model).await?;
+            eprintln!("[openworld] New title: {}", title);
+            {
+                let managed_state = app.state::<Mutex<AppState>>();
+                let mut app_state = managed_state.lock().map_err(|e| e.to_string())?;
+                if app_state.incognito.contains(conversation_id) {
+                    app_state.incognito.update_conversation_title(conversation_id, &title)?;
+                } else {
+                    job.storage.update_conversation_title(conversation_id, &title)?;
+                }
+            }
+            // Tell the frontend to refresh the conversation list
+            let _ = app.emit("conversation-title-updated", ());
+            Ok(())
+        }
+        Task::ExtractFacts {
+            conversation_id,
+            messages,
+            chat_model,
+            existing_memories,
+            pending_memories,
+            source_message_ids,
+        } => {
+            let model = utility_model.as_deref().unwrap_or(chat_model);
+            eprintln!("[openworld] Starting background fact extraction with {}...", model);
+            let ops = chat::extract_facts_from_conversation(messages, model, existing_memories, pending_memories).await?;
+            let ops = embeddings::deduplicate(&job.storage, &job.config, ops).await;
+            if ops.is_empty() {
+                eprintln!("[openworld] No memory changes discovered.");
+                return Ok(());
+            }
+
+            eprintln!("[openworld] Discovered {} memory change(s)", ops.len());
+            let source = MemorySource {
+                conversation_id: conversation_id.clone(),
+                message_ids: source_message_ids.clone(),
+                model: model.to_string(),
+                extracted_at: chrono::Utc::now().to_rfc3339(),
+            };
+            if job.config.memory_review_mode {
+                let mut queued = 0;
+                for op in &ops {
+                    match job.storage.add_pending_operation(op, &source) {
+                        Ok(_) => queued += 1,
+                        Err(e) => eprintln!("[openworld]   Failed to queue memory change: {}", e),
+                    }
+                }
+                if queued > 0 {
+                    // Tell the frontend there are facts waiting for review
+                    let _ = app.emit("memory-review-pending", queued);
+                }
+            } else if let Err(e) = job.storage.apply_memory_operations(&ops, &source) {
+                eprintln!("[openworld]   Failed to apply memory changes: {}", e);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        assert_eq!(retry_delay(1).as_secs(), 2);
+        assert_eq!(retry_delay(2).as_secs(), 4);
+        assert_eq!(retry_delay(3).as_secs(), 8);
+    }
+
+    #[test]
+    fn test_chat_activity_counts() {
+        let before = ACTIVE_CHATS.load(Ordering::SeqCst);
+        let activity = ChatActivity::begin();
+        assert_eq!(ACTIVE_CHATS.load(Ordering::SeqCst), before + 1);
+        drop(activity);
+        assert_eq!(ACTIVE_CHATS.load(Ordering::SeqCst), before);
+    }
+}
@@ -1,20 +1,110 @@
 mod chat;
 mod config;
 mod crypto;
+mod embeddings;
+mod engine_logs;
+mod engine_process;
+mod export;
+mod importer;
+mod incognito;
+mod installer;
+mod jobs;
 mod ollama;
+mod ollama_error;
+mod profiles;
+mod prompts;
+mod pulls;
+mod retention;
 mod storage;
 
-use chat::ChatMessage;
-use config::AppConfig;
+use chat::{ChatMessage, GenerationOptions};
+use config::{AppConfig, EngineConfig};
+use embeddings::{FactDedup, MemoryMergeProposal};
+use export::ExportFormat;
+use importer::ImportSummary;
+use incognito::IncognitoStore;
+use jobs::{Job, JobQueue, Task};
 use ollama::ModelInfo;
+use ollama_error::OllamaError;
+use profiles::Profile;
+use prompts::{PromptImportSummary, RenderedPrompt};
+use pulls::{PullInfo, PullManager};
 use serde::{Deserialize, Serialize};
-use storage::{Conversation, Message, StorageEngine};
-use std::sync::Mutex;
-use tauri::{Manager, Emitter};
+use storage::{
+    Conversation, ConversationFilter, Folder, Memory, MemoryCategory, MemoryFact, MemoryMode,
+    MemoryOperation, MemoryProvenance, MemoryVersion, Message, MessageEdit, PendingMemory, Persona, PersonaDraft, PromptTemplate,
+    PromptTemplateDraft, StorageEngine, Tag,
+    DEFAULT_MEMORY_IMPORTANCE,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager};
 use tauri::State;
 
 pub struct AppState {
-    storage: StorageEngine,
+    /// Storage of the active profile; shared so background jobs keep writing to the
+    /// profile they started in after a switch
+    storage: Arc<StorageEngine>,
+    incognito: IncognitoStore,
+    profile: Profile,
+}
+
+impl AppState {
+    /// Memory mode of a conversation; incognito chats are always `Off`
+    fn memory_mode(&self, conversation_id: &str) -> Result<MemoryMode, String> {
+        if self.incognito.contains(conversation_id) {
+            return Ok(MemoryMode::Off);
+        }
+        Ok(self
+            .storage
+            .get_conversation(conversation_id)?
+            .map(|c| c.memory_mode)
+            .unwrap_or_default())
+    }
+
+    /// Persona the conversation was created with, if it still exists
+    fn conversation_persona(&self, conversation_id: &str) -> Result<Option<Persona>, String> {
+        let persona_id = match self.incognito.get_conversation(conversation_id) {
+            Some(convo) => convo.persona_id,
+            None => self.storage.get_conversation(conversation_id)?.and_then(|c| c.persona_id),
+        };
+        match persona_id {
+            Some(id) => self.storage.get_persona(&id),
+            None => Ok(None),
+        }
+    }
+
+    fn add_message(&mut self, conversation_id: &str, role: &str, content: &str) -> Result<Message, String> {
+        if self.incognito.contains(conversation_id) {
+            self.incognito.add_message(conversation_id, role, content)
+        } else {
+            self.storage.add_message(conversation_id, role, content)
+        }
+    }
+
+    fn update_conversation_title(&mut self, id: &str, title: &str) -> Result<(), String> {
+        if self.incognito.contains(id) {
+            self.incognito.update_conversation_title(id, title)
+        } else {
+            self.storage.update_conversation_title(id, title)
+        }
+    }
+
+    /// Fails for incognito chats, which can't be organized, exported or keep edit history
+    fn require_saved(&self, conversation_id: &str) -> Result<(), String> {
+        if self.incognito.contains(conversation_id) {
+            return Err("Not available for incognito chats".to_string());
+        }
+        Ok(())
+    }
+
+    fn get_messages(&self, conversation_id: &str) -> Result<Vec<Message>, String> {
+        if self.incognito.contains(conversation_id) {
+            self.incognito.get_messages(conversation_id)
+        } else {
+            self.storage.get_messages(conversation_id)
+        }
+    }
 }
 
 // ── Config Commands ──────────────────────────────────────────────────────
@@ -29,6 +119,19 @@ fn save_config_cmd(cfg: AppConfig) -> Result<(), String> {
     config::save_config(&cfg)
 }
 
+/// Settings of the AI engine, shared by all profiles
+#[tauri::command]
+fn get_engine_config() -> Result<EngineConfig, String> {
+    Ok(config::load_engine_config())
+}
+
+/// Save the engine settings and restart the engine with them. Returns the settings in
+/// effect, which can differ if the engine had to move to another port.
+#[tauri::command]
+async fn save_engine_config(app: tauri::AppHandle, cfg: EngineConfig) -> Result<EngineConfig, String> {
+    ollama::apply_engine_config(app, cfg).await
+}
+
 // ── Ollama Commands ──────────────────────────────────────────────────────
 
 #[tauri::command]
@@ -42,7 +145,38 @@ async fn ensure_ollama(app: tauri::AppHandle) -> Result<(), String> {
 }
 
 #[tauri::command]
-async fn list_models() -> Result<Vec<ModelInfo>, String> {
+async fn check_engine_update() -> Result<ollama::EngineUpdateInfo, String> {
+    ollama::check_engine_update().await
+}
+
+#[tauri::command]
+async fn update_engine(app: tauri::AppHandle) -> Result<String, String> {
+    ollama::update_engine(app).await
+}
+
+#[tauri::command]
+async fn rollback_engine(app: tauri::AppHandle) -> Result<Option<String>, String> {
+    ollama::rollback_engine(app).await
+}
+
+#[tauri::command]
+async fn install_engine_from_file(app: tauri::AppHandle, path: String) -> Result<Option<String>, String> {
+    ollama::install_engine_from_file(app, path).await
+}
+
+#[tauri::command]
+async fn get_engine_status() -> Result<ollama::EngineStatus, String> {
+    Ok(ollama::get_engine_status().await)
+}
+
+/// Engine output newer than `since` (a line's `seq`), at `level` or above
+#[tauri::command]
+fn get_engine_logs(since: Option<u64>, level: Option<engine_logs::LogLevel>) -> Vec<engine_logs::EngineLogLine> {
+    engine_logs::get_logs(since, level)
+}
+
+#[tauri::command]
+async fn list_models() -> Result<Vec<ModelInfo>, OllamaError> {
     // Wait for Ollama to be ready (it starts in background on app launch)
     let url = format!("{}/api/tags", ollama::get_ollama_url());
     let client = reqwest::Client::new();
@@ -67,19 +201,31 @@ async fn list_models() -> Result<Vec<ModelInfo>, String> {
     }
 
     if !ready {
-        return Err("AI engine is still starting. Please try again in a moment.".to_string());
+        return Err(OllamaError::EngineNotRunning);
     }
 
     ollama::list_installed_models().await
 }
 
+/// Queue a model download and return the pull's id. Progress arrives as `model-pull-progress`
+/// events and the outcome as a `model-pull-finished` event, both tagged with that id.
+#[tauri::command]
+fn pull_model(pulls: State<'_, PullManager>, model_name: String) -> Result<String, String> {
+    pulls.enqueue(&model_name)
+}
+
+#[tauri::command]
+fn cancel_pull(pulls: State<'_, PullManager>, pull_id: String) -> Result<(), String> {
+    pulls.cancel(&pull_id)
+}
+
 #[tauri::command]
-async fn pull_model(app: tauri::AppHandle, model_name: String) -> Result<(), String> {
-    ollama::pull_model(app, model_name).await
+fn list_pulls(pulls: State<'_, PullManager>) -> Vec<PullInfo> {
+    pulls.list()
 }
 
 #[tauri::command]
-async fn delete_model(model_name: String) -> Result<(), String> {
+async fn delete_model(model_name: String) -> Result<(), OllamaError> {
     ollama::delete_model(&model_name).await
 }
 
@@ -89,125 +235,287 @@ async fn delete_model(model_name: String) -> Result<(), String> {
 async fn send_message(
     app: tauri::AppHandle,
     state: State<'_, Mutex<AppState>>,
+    queue: State<'_, JobQueue>,
     conversation_id: String,
     messages: Vec<ChatMessage>,
     model: String,
+    options: Option<GenerationOptions>,
 ) -> Result<String, String> {
-    // Read memory context and existing memories for deduplication
-    let (memory_context, existing_memories) = {
+    // Read the conversation's memory mode, memory context, existing memories (for
+    // updates/deduplication) and facts still awaiting review so they aren't suggested again
+    let (storage, incognito, persona, memory_mode, memory_context, existing_memories, pending_memories) = {
         let app_state = state.lock().map_err(|e| e.to_string())?;
-        let ctx = app_state.storage.get_memory_context().unwrap_or_default();
-        let mems: Vec<String> = app_state
+        let incognito = app_state.incognito.contains(&conversation_id);
+        let persona = app_state.conversation_persona(&conversation_id)?;
+        let mode = app_state.memory_mode(&conversation_id)?;
+        let ctx = if mode.reads() {
+            app_state.storage.get_memory_context().unwrap_or_default()
+        } else {
+            String::new()
+        };
+        let mems: Vec<(String, String)> = app_state
             .storage
             .list_memories()
             .unwrap_or_default()
             .into_iter()
-            .map(|(_, content, _)| content)
+            .map(|m| (m.id, m.content))
             .collect();
-        (ctx, mems)
+        let pending: Vec<String> = app_state
+            .storage
+            .list_pending_memories()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| p.content)
+            .collect();
+        (app_state.storage.clone(), incognito, persona, mode, ctx, mems, pending)
     };
+    let config = config::load_config();
 
-    // Clone app handle before it's moved into send_chat_message
-    let app_for_extraction = app.clone();
+    // Send to Ollama and stream response (memory context is passed for system prompt injection).
+    // Background jobs wait while the reply is streaming.
+    let full_response = {
+        let _activity = jobs::ChatActivity::begin();
+        chat::send_chat_message(
+            app,
+            conversation_id.clone(),
+            messages.clone(),
+            model.clone(),
+            memory_context,
+            persona.as_ref().map(|p| p.system_prompt.clone()),
+            // Options given with the message (e.g. from a prompt template) win over the persona's
+            options.or(persona.map(|p| p.options)).unwrap_or_default(),
+        )
+        .await?
+    };
 
-    // Send to Ollama and stream response (memory context is passed for system prompt injection)
-    let full_response =
-        chat::send_chat_message(app, conversation_id.clone(), messages.clone(), model.clone(), memory_context).await?;
+    // Save the assistant response to the storage captured above (the active profile may
+    // have changed while streaming), and remember which messages the fact extractor will
+    // look at so saved memories can point back to them
+    let source_message_ids = if incognito {
+        let mut app_state = state.lock().map_err(|e| e.to_string())?;
+        app_state.incognito.add_message(&conversation_id, "assistant", &full_response)?;
+        Vec::new()
+    } else {
+        storage.add_message(&conversation_id, "assistant", &full_response)?;
+        storage
+            .recent_message_ids(&conversation_id, chat::FACT_EXTRACTION_WINDOW)
+            .unwrap_or_default()
+    };
 
-    // Save the assistant response to storage
-    {
-        let app_state = state.lock().map_err(|e| e.to_string())?;
-        app_state
-            .storage
-            .add_message(&conversation_id, "assistant", &full_response)?;
+    // Title generation and fact extraction run on the background queue — don't block the response
+    let mut all_msgs = messages;
+    all_msgs.push(ChatMessage {
+        role: "assistant".to_string(),
+        content: full_response.clone(),
+    });
+
+    // Generate dynamic title if this is the first exchange
+    if all_msgs.len() <= 2 {
+        queue.enqueue(Job {
+            storage: storage.clone(),
+            config: config.clone(),
+            task: Task::GenerateTitle {
+                conversation_id: conversation_id.clone(),
+                messages: all_msgs.clone(),
+                chat_model: model.clone(),
+            },
+        });
     }
 
-    // Background fact extraction — don't block the response
-    let messages_for_extraction = messages;
-    let model_for_extraction = model;
-    let response_for_extraction = full_response.clone();
-
-    tauri::async_runtime::spawn(async move {
-        // Build full message list including the assistant's reply
-        let mut all_msgs = messages_for_extraction;
-        all_msgs.push(ChatMessage {
-            role: "assistant".to_string(),
-            content: response_for_extraction,
+    if memory_mode.writes() {
+        queue.enqueue(Job {
+            storage,
+            config,
+            task: Task::ExtractFacts {
+                conversation_id,
+                messages: all_msgs,
+                chat_model: model,
+                existing_memories,
+                pending_memories,
+                source_message_ids,
+            },
         });
+    } else {
+        eprintln!("[openworld] Memory is {} for this conversation, skipping fact extraction.", memory_mode.as_str());
+    }
 
-        // Generate dynamic title if this is the first exchange
-        if all_msgs.len() <= 2 {
-            eprintln!("[openworld] Generating dynamic title...");
-            if let Ok(title) = chat::generate_conversation_title(&all_msgs, &model_for_extraction).await {
-                eprintln!("[openworld] New title: {}", title);
-                let managed_state = app_for_extraction.state::<Mutex<AppState>>();
-                if let Ok(app_state) = managed_state.lock() {
-                    let _ = app_state.storage.update_conversation_title(&conversation_id, &title);
-                }
-                // Tell the frontend to refresh the conversation list
-                let _ = app_for_extraction.emit("conversation-title-updated", ());
-            }
-        }
+    Ok(full_response)
+}
 
-        eprintln!("[openworld] Starting background fact extraction...");
-        match chat::extract_facts_from_conversation(&all_msgs, &model_for_extraction, &existing_memories).await {
-            Ok(facts) if facts.is_empty() => {
-                eprintln!("[openworld] No new facts discovered.");
-            }
-            Ok(facts) => {
-                eprintln!("[openworld] Discovered {} new fact(s):", facts.len());
-                {
-                    let managed_state = app_for_extraction.state::<Mutex<AppState>>();
-                    let app_state = managed_state.lock().unwrap();
-                    for fact in &facts {
-                        eprintln!("[openworld]   + {}", fact);
-                        if let Err(e) = app_state.storage.add_memory(fact) {
-                            eprintln!("[openworld]   Failed to save fact: {}", e);
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                eprintln!("[openworld] Fact extraction failed (non-fatal): {}", e);
-            }
-        }
-    });
+/// Edit a user message, drop everything after it and regenerate the assistant reply
+#[tauri::command]
+async fn edit_and_resend(
+    app: tauri::AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    queue: State<'_, JobQueue>,
+    message_id: String,
+    new_content: String,
+    model: String,
+) -> Result<String, String> {
+    let (conversation_id, history) = {
+        let mut app_state = state.lock().map_err(|e| e.to_string())?;
+        let edited = match app_state.incognito.edit_message(&message_id, &new_content)? {
+            Some(edited) => edited,
+            None => app_state.storage.edit_message(&message_id, &new_content)?,
+        };
+        let history = app_state.get_messages(&edited.conversation_id)?;
+        (edited.conversation_id, history)
+    };
 
-    Ok(full_response)
+    let messages = history
+        .into_iter()
+        .map(|m| ChatMessage {
+            role: m.role,
+            content: m.content,
+        })
+        .collect();
+
+    send_message(app, state, queue, conversation_id, messages, model, None).await
 }
 
 // ── Storage Commands ─────────────────────────────────────────────────────
 
+/// `incognito` conversations are kept in memory only and never touch long-term memory.
+/// With a persona, its default model and memory policy apply unless given here; without
+/// either model, the configured default model is used.
 #[tauri::command]
 fn create_conversation(
     state: State<'_, Mutex<AppState>>,
     title: String,
-    model: String,
+    model: Option<String>,
+    memory_mode: Option<MemoryMode>,
+    incognito: Option<bool>,
+    persona_id: Option<String>,
 ) -> Result<Conversation, String> {
+    let mut app_state = state.lock().map_err(|e| e.to_string())?;
+    let persona = match &persona_id {
+        Some(id) => Some(
+            app_state
+                .storage
+                .get_persona(id)?
+                .ok_or_else(|| format!("Persona not found: {}", id))?,
+        ),
+        None => None,
+    };
+    let model = model
+        .filter(|m| !m.trim().is_empty())
+        .or_else(|| persona.as_ref().and_then(|p| p.default_model.clone()))
+        .unwrap_or_else(|| config::load_config().default_model);
+
+    if incognito.unwrap_or(false) {
+        return Ok(app_state
+            .incognito
+            .create_conversation(&title, &model, persona_id.as_deref()));
+    }
+    let memory_mode = memory_mode
+        .or(persona.map(|p| p.memory_mode))
+        .unwrap_or_default();
+    app_state
+        .storage
+        .create_conversation(&title, &model, memory_mode, persona_id.as_deref())
+}
+
+/// Without a filter, returns every non-archived conversation (pinned first)
+#[tauri::command]
+fn list_conversations(
+    state: State<'_, Mutex<AppState>>,
+    filter: Option<ConversationFilter>,
+) -> Result<Vec<Conversation>, String> {
+    let filter = filter.unwrap_or(ConversationFilter {
+        archived: Some(false),
+        ..Default::default()
+    });
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    let mut convos = app_state.storage.list_conversations(&filter)?;
+
+    // Incognito chats show up in the main list only, ahead of everything else
+    let main_list = !filter.in_trash
+        && filter.archived != Some(true)
+        && filter.folder_id.is_none()
+        && filter.tag_id.is_none()
+        && filter.search.is_none()
+        && filter.offset.unwrap_or(0) == 0;
+    if main_list {
+        convos.splice(0..0, app_state.incognito.list_conversations());
+    }
+    Ok(convos)
+}
+
+#[tauri::command]
+fn set_conversation_memory_mode(
+    state: State<'_, Mutex<AppState>>,
+    id: String,
+    memory_mode: MemoryMode,
+) -> Result<(), String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    if app_state.incognito.contains(&id) {
+        return Err("Incognito conversations never use memory".to_string());
+    }
+    app_state.storage.set_conversation_memory_mode(&id, memory_mode)
+}
+
+#[tauri::command]
+fn set_conversation_pinned(state: State<'_, Mutex<AppState>>, id: String, pinned: bool) -> Result<(), String> {
     let app_state = state.lock().map_err(|e| e.to_string())?;
-    app_state.storage.create_conversation(&title, &model)
+    app_state.require_saved(&id)?;
+    app_state.storage.set_conversation_pinned(&id, pinned)
 }
 
 #[tauri::command]
-fn list_conversations(state: State<'_, Mutex<AppState>>) -> Result<Vec<Conversation>, String> {
+fn set_conversation_archived(state: State<'_, Mutex<AppState>>, id: String, archived: bool) -> Result<(), String> {
     let app_state = state.lock().map_err(|e| e.to_string())?;
-    app_state.storage.list_conversations()
+    app_state.require_saved(&id)?;
+    app_state.storage.set_conversation_archived(&id, archived)
 }
 
 #[tauri::command]
-fn delete_conversation(state: State<'_, Mutex<AppState>>, id: String) -> Result<(), String> {
+fn move_conversation_to_folder(
+    state: State<'_, Mutex<AppState>>,
+    id: String,
+    folder_id: Option<String>,
+) -> Result<(), String> {
     let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.require_saved(&id)?;
+    app_state.storage.move_conversation_to_folder(&id, folder_id.as_deref())
+}
+
+#[tauri::command]
+fn delete_conversation(state: State<'_, Mutex<AppState>>, id: String) -> Result<(), String> {
+    let mut app_state = state.lock().map_err(|e| e.to_string())?;
+    // Incognito chats skip the trash and are gone immediately
+    if app_state.incognito.contains(&id) {
+        app_state.incognito.delete_conversation(&id);
+        return Ok(());
+    }
     app_state.storage.delete_conversation(&id)
 }
 
+#[tauri::command]
+fn restore_conversation(state: State<'_, Mutex<AppState>>, id: String) -> Result<(), String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.restore_conversation(&id)
+}
+
+#[tauri::command]
+fn purge_conversation(state: State<'_, Mutex<AppState>>, id: String) -> Result<(), String> {
+    let storage = state.lock().map_err(|e| e.to_string())?.storage.clone();
+    storage.purge_conversation(&id)
+}
+
+#[tauri::command]
+fn empty_trash(state: State<'_, Mutex<AppState>>) -> Result<usize, String> {
+    let storage = state.lock().map_err(|e| e.to_string())?.storage.clone();
+    storage.empty_trash()
+}
+
 #[tauri::command]
 fn update_conversation_title(
     state: State<'_, Mutex<AppState>>,
     id: String,
     title: String,
 ) -> Result<(), String> {
-    let app_state = state.lock().map_err(|e| e.to_string())?;
-    app_state.storage.update_conversation_title(&id, &title)
+    let mut app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.update_conversation_title(&id, &title)
 }
 
 #[tauri::command]
@@ -217,8 +525,20 @@ fn add_message(
     role: String,
     content: String,
 ) -> Result<Message, String> {
+    let mut app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.add_message(&conversation_id, &role, &content)
+}
+
+#[tauri::command]
+fn get_message_edits(
+    state: State<'_, Mutex<AppState>>,
+    message_id: String,
+) -> Result<Vec<MessageEdit>, String> {
     let app_state = state.lock().map_err(|e| e.to_string())?;
-    app_state.storage.add_message(&conversation_id, &role, &content)
+    if app_state.incognito.contains_message(&message_id) {
+        return Err("Not available for incognito chats".to_string());
+    }
+    app_state.storage.get_message_edits(&message_id)
 }
 
 #[tauri::command]
@@ -227,7 +547,183 @@ fn get_messages(
     conversation_id: String,
 ) -> Result<Vec<Message>, String> {
     let app_state = state.lock().map_err(|e| e.to_string())?;
-    app_state.storage.get_messages(&conversation_id)
+    app_state.get_messages(&conversation_id)
+}
+
+// ── Persona Commands ─────────────────────────────────────────────────────
+
+#[tauri::command]
+fn create_persona(state: State<'_, Mutex<AppState>>, persona: PersonaDraft) -> Result<Persona, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.create_persona(&persona)
+}
+
+#[tauri::command]
+fn update_persona(state: State<'_, Mutex<AppState>>, id: String, persona: PersonaDraft) -> Result<(), String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.update_persona(&id, &persona)
+}
+
+#[tauri::command]
+fn list_personas(state: State<'_, Mutex<AppState>>) -> Result<Vec<Persona>, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.list_personas()
+}
+
+#[tauri::command]
+fn delete_persona(state: State<'_, Mutex<AppState>>, id: String) -> Result<(), String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.delete_persona(&id)
+}
+
+// ── Prompt Template Commands ─────────────────────────────────────────────
+
+#[tauri::command]
+fn create_prompt_template(
+    state: State<'_, Mutex<AppState>>,
+    template: PromptTemplateDraft,
+) -> Result<PromptTemplate, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.create_prompt_template(&template)
+}
+
+#[tauri::command]
+fn update_prompt_template(
+    state: State<'_, Mutex<AppState>>,
+    id: String,
+    template: PromptTemplateDraft,
+) -> Result<(), String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.update_prompt_template(&id, &template)
+}
+
+#[tauri::command]
+fn list_prompt_templates(state: State<'_, Mutex<AppState>>) -> Result<Vec<PromptTemplate>, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.list_prompt_templates()
+}
+
+#[tauri::command]
+fn delete_prompt_template(state: State<'_, Mutex<AppState>>, id: String) -> Result<(), String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.delete_prompt_template(&id)
+}
+
+/// Fill in a template's variables; fails listing any that are missing, so nothing half-filled gets sent
+#[tauri::command]
+fn render_prompt(
+    state: State<'_, Mutex<AppState>>,
+    template_id: String,
+    vars: HashMap<String, String>,
+) -> Result<RenderedPrompt, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    prompts::render_template(&app_state.storage, &template_id, &vars)
+}
+
+#[tauri::command]
+fn export_prompt_templates(state: State<'_, Mutex<AppState>>) -> Result<String, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    prompts::export_library(&app_state.storage)
+}
+
+#[tauri::command]
+fn import_prompt_templates(state: State<'_, Mutex<AppState>>, path: String) -> Result<PromptImportSummary, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    prompts::import_library_file(&app_state.storage, &path)
+}
+
+// ── Folder & Tag Commands ────────────────────────────────────────────────
+
+#[tauri::command]
+fn create_folder(state: State<'_, Mutex<AppState>>, name: String) -> Result<Folder, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.create_folder(&name)
+}
+
+#[tauri::command]
+fn list_folders(state: State<'_, Mutex<AppState>>) -> Result<Vec<Folder>, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.list_folders()
+}
+
+#[tauri::command]
+fn rename_folder(state: State<'_, Mutex<AppState>>, id: String, name: String) -> Result<(), String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.rename_folder(&id, &name)
+}
+
+#[tauri::command]
+fn delete_folder(state: State<'_, Mutex<AppState>>, id: String) -> Result<(), String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.delete_folder(&id)
+}
+
+#[tauri::command]
+fn create_tag(state: State<'_, Mutex<AppState>>, name: String) -> Result<Tag, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.create_tag(&name)
+}
+
+#[tauri::command]
+fn list_tags(state: State<'_, Mutex<AppState>>) -> Result<Vec<Tag>, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.list_tags()
+}
+
+#[tauri::command]
+fn delete_tag(state: State<'_, Mutex<AppState>>, id: String) -> Result<(), String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.delete_tag(&id)
+}
+
+#[tauri::command]
+fn tag_conversation(
+    state: State<'_, Mutex<AppState>>,
+    conversation_id: String,
+    tag_id: String,
+) -> Result<(), String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.require_saved(&conversation_id)?;
+    app_state.storage.tag_conversation(&conversation_id, &tag_id)
+}
+
+#[tauri::command]
+fn untag_conversation(
+    state: State<'_, Mutex<AppState>>,
+    conversation_id: String,
+    tag_id: String,
+) -> Result<(), String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.require_saved(&conversation_id)?;
+    app_state.storage.untag_conversation(&conversation_id, &tag_id)
+}
+
+// ── Export / Import Commands ─────────────────────────────────────────────
+
+#[tauri::command]
+fn export_conversation(
+    state: State<'_, Mutex<AppState>>,
+    id: String,
+    format: ExportFormat,
+) -> Result<String, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.require_saved(&id)?;
+    export::export_conversation(&app_state.storage, &id, format)
+}
+
+#[tauri::command]
+fn export_all_conversations(
+    state: State<'_, Mutex<AppState>>,
+    format: ExportFormat,
+) -> Result<String, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    export::export_all_conversations(&app_state.storage, format)
+}
+
+#[tauri::command]
+fn import_history(state: State<'_, Mutex<AppState>>, path: String) -> Result<ImportSummary, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    importer::import_file(&app_state.storage, &path)
 }
 
 use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind, System};
@@ -267,14 +763,10 @@ fn get_system_metrics() -> Result<SystemMetrics, String> {
 
     let cpu_usage = sys.global_cpu_usage();
     
-    // Get database size at ~/.openworld/data.db
-    let db_size_bytes = if let Some(mut path) = dirs::home_dir() {
-        path.push(".openworld");
-        path.push("data.db");
-        std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
-    } else {
-        0
-    };
+    // Size of the active profile's database
+    let db_size_bytes = std::fs::metadata(config::profile_dir().join("data.db"))
+        .map(|m| m.len())
+        .unwrap_or(0);
 
     Ok(SystemMetrics {
         total_ram: sys.total_memory(),
@@ -284,31 +776,103 @@ fn get_system_metrics() -> Result<SystemMetrics, String> {
     })
 }
 
-// ── Memory Commands ─────────────────────────────────────────────────────
+// ── Profile Commands ─────────────────────────────────────────────────────
 
-#[derive(Debug, Serialize, Deserialize)]
-struct MemoryItem {
+#[tauri::command]
+fn list_profiles() -> Vec<Profile> {
+    profiles::list_profiles()
+}
+
+#[tauri::command]
+fn get_active_profile(state: State<'_, Mutex<AppState>>) -> Result<Profile, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    Ok(app_state.profile.clone())
+}
+
+/// Without a passphrase the profile's key is stored in a keyfile next to its database
+#[tauri::command]
+fn create_profile(name: String, passphrase: Option<String>) -> Result<Profile, String> {
+    profiles::create_profile(&name, passphrase.as_deref())
+}
+
+/// Swap the active database, memories and config for another profile's.
+/// Incognito conversations are discarded on switch.
+#[tauri::command]
+fn switch_profile(
+    app: tauri::AppHandle,
+    state: State<'_, Mutex<AppState>>,
     id: String,
-    content: String,
-    created_at: String,
+    passphrase: Option<String>,
+) -> Result<Profile, String> {
+    let profile = profiles::get_profile(&id)?;
+    let storage = profiles::open_profile(&profile, passphrase.as_deref())?;
+    {
+        let mut app_state = state.lock().map_err(|e| e.to_string())?;
+        app_state.storage = Arc::new(storage);
+        app_state.incognito = IncognitoStore::new();
+        app_state.profile = profile.clone();
+        config::set_profile_dir(profiles::profile_dir(&profile.id));
+    }
+    profiles::set_active_profile(&profile.id)?;
+    let _ = app.emit("profile-switched", &profile);
+    Ok(profile)
 }
 
+// ── Memory Commands ─────────────────────────────────────────────────────
+
 #[tauri::command]
-fn add_memory_cmd(
+async fn add_memory_cmd(
     state: State<'_, Mutex<AppState>>,
     content: String,
+    category: Option<MemoryCategory>,
+    importance: Option<u8>,
+    expires_at: Option<String>,
 ) -> Result<String, String> {
-    let app_state = state.lock().map_err(|e| e.to_string())?;
-    app_state.storage.add_memory(&content)
+    let expires_at = match expires_at.as_deref().map(str::trim).filter(|e| !e.is_empty()) {
+        Some(raw) => Some(chat::normalize_expiry(raw).ok_or_else(|| {
+            format!("Invalid expiry date \"{}\": use YYYY-MM-DD or an RFC 3339 timestamp", raw)
+        })?),
+        None => None,
+    };
+    let storage = state.lock().map_err(|e| e.to_string())?.storage.clone();
+    let fact = MemoryFact {
+        category: category.unwrap_or_default(),
+        importance: importance.unwrap_or(DEFAULT_MEMORY_IMPORTANCE).clamp(1, 5),
+        expires_at,
+        ..MemoryFact::new(&content)
+    };
+    // A fact that is already known updates the stored memory instead of duplicating it
+    match embeddings::deduplicate_fact(&storage, &config::load_config(), fact).await {
+        FactDedup::New(fact) => storage.add_memory(&fact),
+        FactDedup::Merge { id, fact } => storage.save_memory_operation(&MemoryOperation::Update { id, fact }),
+        FactDedup::Duplicate { id } => Ok(id),
+    }
 }
 
 #[tauri::command]
 fn list_memories_cmd(
     state: State<'_, Mutex<AppState>>,
-) -> Result<Vec<MemoryItem>, String> {
+) -> Result<Vec<Memory>, String> {
     let app_state = state.lock().map_err(|e| e.to_string())?;
-    let raw = app_state.storage.list_memories()?;
-    Ok(raw.into_iter().map(|(id, content, created_at)| MemoryItem { id, content, created_at }).collect())
+    app_state.storage.list_memories()
+}
+
+/// Find groups of near-duplicate memories and suggest how to merge them
+#[tauri::command]
+async fn consolidate_memories(state: State<'_, Mutex<AppState>>) -> Result<Vec<MemoryMergeProposal>, String> {
+    let storage = state.lock().map_err(|e| e.to_string())?.storage.clone();
+    embeddings::consolidate_memories(&storage).await
+}
+
+/// Apply a merge: the first memory keeps `content`, the others are removed
+#[tauri::command]
+fn merge_memories(
+    state: State<'_, Mutex<AppState>>,
+    memory_ids: Vec<String>,
+    content: String,
+) -> Result<String, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.merge_memories(&memory_ids, &content)
 }
 
 #[tauri::command]
@@ -320,6 +884,68 @@ fn delete_memory_cmd(
     app_state.storage.delete_memory(&id)
 }
 
+#[tauri::command]
+fn get_memory_history(
+    state: State<'_, Mutex<AppState>>,
+    id: String,
+) -> Result<Vec<MemoryVersion>, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.get_memory_history(&id)
+}
+
+#[tauri::command]
+fn list_pending_memories(state: State<'_, Mutex<AppState>>) -> Result<Vec<PendingMemory>, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.list_pending_memories()
+}
+
+/// Approve a pending memory, optionally with edited text. A proposed addition that has
+/// become a duplicate since it was queued updates the matching memory instead.
+async fn approve_pending(state: State<'_, Mutex<AppState>>, id: String, content: Option<String>) -> Result<String, String> {
+    let storage = state.lock().map_err(|e| e.to_string())?.storage.clone();
+    let op = match storage.pending_memory_operation(&id, content.as_deref())? {
+        MemoryOperation::Add { fact } => match embeddings::deduplicate_fact(&storage, &config::load_config(), fact).await {
+            FactDedup::New(fact) => MemoryOperation::Add { fact },
+            FactDedup::Merge { id, fact } => MemoryOperation::Update { id, fact },
+            FactDedup::Duplicate { id: existing } => {
+                storage.reject_pending_memory(&id)?;
+                return Ok(existing);
+            }
+        },
+        op => op,
+    };
+    storage.approve_pending_memory(&id, &op)
+}
+
+#[tauri::command]
+async fn approve_memory(state: State<'_, Mutex<AppState>>, id: String) -> Result<String, String> {
+    approve_pending(state, id, None).await
+}
+
+#[tauri::command]
+async fn edit_and_approve(
+    state: State<'_, Mutex<AppState>>,
+    id: String,
+    content: String,
+) -> Result<String, String> {
+    approve_pending(state, id, Some(content)).await
+}
+
+#[tauri::command]
+fn reject_memory(state: State<'_, Mutex<AppState>>, id: String) -> Result<(), String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.reject_pending_memory(&id)
+}
+
+#[tauri::command]
+fn get_memory_provenance(
+    state: State<'_, Mutex<AppState>>,
+    id: String,
+) -> Result<MemoryProvenance, String> {
+    let app_state = state.lock().map_err(|e| e.to_string())?;
+    app_state.storage.get_memory_provenance(&id)
+}
+
 #[tauri::command]
 fn get_memory_context_cmd(
     state: State<'_, Mutex<AppState>>,
@@ -330,44 +956,113 @@ fn get_memory_context_cmd(
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let storage = StorageEngine::new().expect("Failed to initialize storage");
-    let app_state = Mutex::new(AppState { storage });
+    let profile = profiles::startup_profile();
+    config::set_profile_dir(profiles::profile_dir(&profile.id));
+    let storage = profiles::open_profile(&profile, None).expect("Failed to initialize storage");
+    let app_state = Mutex::new(AppState {
+        storage: Arc::new(storage),
+        incognito: IncognitoStore::new(),
+        profile,
+    });
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .manage(app_state)
         .setup(|app| {
+            engine_logs::init(app.handle().clone());
+
             // Auto-start Ollama on every app launch
             let handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
+                // A previous run that crashed may have left its engine running on our port
+                let _ = tauri::async_runtime::spawn_blocking(engine_process::cleanup_orphan).await;
                 eprintln!("[openworld] App startup: auto-starting Ollama...");
                 match ollama::ensure_ollama_ready(handle).await {
                     Ok(()) => eprintln!("[openworld] App startup: Ollama is ready!"),
                     Err(e) => eprintln!("[openworld] App startup: Ollama failed to start: {}", e),
                 }
             });
+
+            // Trash purge and retention policy
+            tauri::async_runtime::spawn(retention::run(app.handle().clone()));
+            app.manage(jobs::start(app.handle().clone()));
+            app.manage(pulls::start(app.handle().clone()));
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             get_config,
             save_config_cmd,
+            get_engine_config,
+            save_engine_config,
             check_ollama,
             ensure_ollama,
+            check_engine_update,
+            update_engine,
+            rollback_engine,
+            install_engine_from_file,
+            get_engine_status,
+            get_engine_logs,
             list_models,
             pull_model,
+            cancel_pull,
+            list_pulls,
             delete_model,
             send_message,
+            edit_and_resend,
             create_conversation,
             list_conversations,
+            set_conversation_memory_mode,
+            set_conversation_pinned,
+            set_conversation_archived,
+            move_conversation_to_folder,
             delete_conversation,
+            restore_conversation,
+            purge_conversation,
+            empty_trash,
             update_conversation_title,
             add_message,
             get_messages,
+            get_message_edits,
+            create_persona,
+            update_persona,
+            list_personas,
+            delete_persona,
+            create_prompt_template,
+            update_prompt_template,
+            list_prompt_templates,
+            delete_prompt_template,
+            render_prompt,
+            export_prompt_templates,
+            import_prompt_templates,
+            create_folder,
+            list_folders,
+            rename_folder,
+            delete_folder,
+            create_tag,
+            list_tags,
+            delete_tag,
+            tag_conversation,
+            untag_conversation,
+            export_conversation,
+            export_all_conversations,
+            import_history,
             get_system_memory,
             get_system_metrics,
             add_memory_cmd,
             list_memories_cmd,
+            list_profiles,
+            get_active_profile,
+            create_profile,
+            switch_profile,
+            consolidate_memories,
+            merge_memories,
             delete_memory_cmd,
+            get_memory_provenance,
+            get_memory_history,
+            list_pending_memories,
+            approve_memory,
+            edit_and_approve,
+            reject_memory,
             get_memory_context_cmd,
         ])
         .build(tauri::generate_context!())
@@ -1,12 +1,19 @@
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::io::Write;
-use std::path::PathBuf;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
+use tokio::sync::Notify;
 
-use crate::config::{get_data_dir, load_config};
+use crate::config::{get_data_dir, load_engine_config, save_engine_config, EngineConfig};
+use crate::engine_logs;
+use crate::engine_process::{self, EngineOwner};
+use crate::installer;
+use crate::ollama_error::OllamaError;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelInfo {
@@ -49,10 +56,13 @@ struct OllamaModelDetails {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PullProgress {
+    #[serde(default)]
     pub status: String,
     pub digest: Option<String>,
     pub total: Option<u64>,
     pub completed: Option<u64>,
+    /// Set instead of a status when the pull fails
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -60,17 +70,72 @@ pub struct OllamaStatus {
     pub stage: String,   // "checking" | "downloading" | "starting" | "ready" | "error"
     pub message: String,
     pub progress: Option<f64>,
+    /// Engine version from `ollama --version`, sent with the "ready" stage
+    pub version: Option<String>,
+    /// Whether the engine that is ready is ours or an Ollama that was already running
+    pub owner: Option<EngineOwner>,
+}
+
+/// What `check_engine_update` found
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EngineUpdateInfo {
+    /// Version of the bundled engine (None if unknown or not installed)
+    pub installed: Option<String>,
+    /// Version `update_engine` would install: the pinned one, or the latest release
+    pub available: String,
+    pub pinned: bool,
+    pub update_available: bool,
+    /// Version kept for `rollback_engine`, if any
+    pub previous: Option<String>,
 }
 
 // ── Global Ollama process handle ─────────────────────────────────────────
 
+/// Ollama's standard port, used when `ollama_host` doesn't name one
+const DEFAULT_ENGINE_PORT: u16 = 11434;
+/// Restarts in a row after which the supervisor stops trying
+const MAX_RESTARTS: u32 = 5;
+/// Delay before the first restart; doubles with every further crash in a row
+const RESTART_BASE_DELAY_SECS: u64 = 1;
+/// An engine that has been up this long counts as healthy again and the backoff resets
+const STABLE_AFTER_SECS: u64 = 60;
+const SUPERVISOR_POLL_MS: u64 = 1000;
+
+/// Snapshot of the managed engine process, returned by `get_engine_status`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EngineStatus {
+    pub owner: EngineOwner,
+    /// The managed process is running (an external engine doesn't count)
+    pub running: bool,
+    pub pid: Option<u32>,
+    pub uptime_secs: Option<u64>,
+    /// Restarts performed by the supervisor since the app started
+    pub restart_count: u32,
+    /// Exit code of the last time the engine exited (None if it never did or was killed by a signal)
+    pub last_exit_code: Option<i32>,
+}
+
+/// Bookkeeping the supervisor needs about the managed process
+#[derive(Default)]
+struct EngineRuntime {
+    binary_path: Option<String>,
+    started_at: Option<Instant>,
+    restart_count: u32,
+    consecutive_crashes: u32,
+    last_exit_code: Option<i32>,
+    /// Set by `stop_ollama`, so an intentional stop isn't treated as a crash
+    stopped: bool,
+}
+
 lazy_static::lazy_static! {
     static ref OLLAMA_PROCESS: Mutex<Option<Child>> = Mutex::new(None);
+    static ref ENGINE_RUNTIME: Mutex<EngineRuntime> = Mutex::new(EngineRuntime::default());
 }
 
+static SUPERVISOR_STARTED: AtomicBool = AtomicBool::new(false);
+
 pub fn get_ollama_url() -> String {
-    let config = load_config();
-    config.ollama_host
+    load_engine_config().ollama_host
 }
 
 fn get_ollama_bin_dir() -> PathBuf {
@@ -80,13 +145,16 @@ fn get_ollama_bin_dir() -> PathBuf {
     bin_dir
 }
 
-fn get_ollama_bin_path() -> PathBuf {
-    let name = if cfg!(target_os = "windows") {
+fn binary_name() -> &'static str {
+    if cfg!(target_os = "windows") {
         "ollama.exe"
     } else {
         "ollama"
-    };
-    get_ollama_bin_dir().join(name)
+    }
+}
+
+fn get_ollama_bin_path() -> PathBuf {
+    get_ollama_bin_dir().join(binary_name())
 }
 
 /// Search for Ollama binary in common locations + PATH
@@ -135,183 +203,170 @@ fn find_ollama_binary() -> Option<String> {
     None
 }
 
-/// Get the GitHub release download URL and whether it needs extraction
-fn get_download_info() -> Result<(String, bool), String> {
+/// Get the release asset name for this platform and whether it needs extraction
+fn get_download_info() -> Result<(&'static str, bool), String> {
     let os = std::env::consts::OS;
     let arch = std::env::consts::ARCH;
 
-    // Use latest release redirect from GitHub
     match (os, arch) {
-        ("macos", "aarch64") | ("macos", "x86_64") => {
-            // macOS: .tgz archive containing the ollama binary
-            Ok(("https://github.com/ollama/ollama/releases/latest/download/ollama-darwin.tgz".to_string(), true))
-        }
-        ("linux", "x86_64") => {
-            Ok(("https://github.com/ollama/ollama/releases/latest/download/ollama-linux-amd64.tar.zst".to_string(), true))
-        }
-        ("linux", "aarch64") => {
-            Ok(("https://github.com/ollama/ollama/releases/latest/download/ollama-linux-arm64.tar.zst".to_string(), true))
-        }
+        // macOS: .tgz archive containing the ollama binary
+        ("macos", "aarch64") | ("macos", "x86_64") => Ok(("ollama-darwin.tgz", true)),
+        ("linux", "x86_64") => Ok(("ollama-linux-amd64.tar.zst", true)),
+        ("linux", "aarch64") => Ok(("ollama-linux-arm64.tar.zst", true)),
         _ => Err(format!("Unsupported platform: {}-{}", os, arch)),
     }
 }
 
-/// Download the Ollama binary from GitHub releases
-async fn download_ollama(app: &AppHandle) -> Result<String, String> {
-    emit_status(app, "downloading", "Downloading AI engine...", Some(0.0));
-
-    let (url, needs_extract) = get_download_info()?;
-    eprintln!("[openworld] Downloading Ollama from: {}", url);
-    eprintln!("[openworld] Needs extraction: {}", needs_extract);
+/// The version to install when none is given explicitly: the pinned one, if any
+fn pinned_engine_version() -> Option<String> {
+    load_engine_config()
+        .pinned_engine_version
+        .filter(|v| !v.trim().is_empty())
+        .map(|v| installer::normalize_version(&v))
+}
 
-    let client = Client::builder()
+fn release_client() -> Result<Client, String> {
+    Client::builder()
         .timeout(std::time::Duration::from_secs(600))
         .build()
-        .map_err(|e| { eprintln!("[openworld] HTTP client error: {}", e); format!("HTTP client error: {}", e) })?;
-
-    let resp = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| { eprintln!("[openworld] Download request failed: {}", e); format!("Download request failed: {}", e) })?;
-
-    eprintln!("[openworld] Download response: HTTP {} (final URL after redirects)", resp.status());
-    if !resp.status().is_success() {
-        let msg = format!("Download failed with HTTP {}", resp.status());
-        eprintln!("[openworld] {}", msg);
-        return Err(msg);
-    }
-
-    let total_size = resp.content_length().unwrap_or(0);
-    eprintln!("[openworld] Download size: {} bytes ({:.1} MB)", total_size, total_size as f64 / 1_048_576.0);
+        .map_err(|e| format!("HTTP client error: {}", e))
+}
 
-    // Save to a temp file (archive or binary)
-    let bin_dir = get_ollama_bin_dir();
-    let download_path = if needs_extract {
-        bin_dir.join("ollama-download.tgz")
-    } else {
-        get_ollama_bin_path()
+/// Fetch the checksum manifest of `version`, or of the latest release if None. For
+/// `latest`, the manifest's final URL tells us which version that currently is.
+async fn resolve_release(client: &Client, version: Option<&str>) -> Result<(installer::ReleaseChecksums, String), String> {
+    let checksums_url = match version {
+        Some(version) => format!("{}/download/{}/{}", installer::RELEASES_BASE_URL, version, installer::CHECKSUMS_ASSET),
+        None => format!("{}/latest/download/{}", installer::RELEASES_BASE_URL, installer::CHECKSUMS_ASSET),
     };
-
-    let mut file = std::fs::File::create(&download_path)
-        .map_err(|e| format!("Failed to create file: {}", e))?;
-
-    let mut downloaded: u64 = 0;
-    let mut stream = resp;
-
-    while let Ok(chunk) = stream.chunk().await {
-        match chunk {
-            Some(bytes) => {
-                file.write_all(&bytes)
-                    .map_err(|e| format!("Write error: {}", e))?;
-                downloaded += bytes.len() as u64;
-                if total_size > 0 {
-                    let pct = downloaded as f64 / total_size as f64;
-                    emit_status(
-                        app,
-                        "downloading",
-                        &format!("Downloading AI engine... {}%", (pct * 100.0) as u32),
-                        Some(pct),
-                    );
-                }
-            }
-            None => break,
-        }
+    let mut checksums = installer::fetch_checksums(client, &checksums_url).await?;
+    if let Some(version) = version {
+        checksums.version = Some(version.to_string());
     }
+    let version = checksums
+        .version
+        .clone()
+        .ok_or("Could not determine which Ollama version the latest release is")?;
+    Ok((checksums, version))
+}
 
-    drop(file);
-    eprintln!("[openworld] Downloaded {} bytes to {}", downloaded, download_path.display());
-
-    // Integrity check: file must be > 1MB (a real binary/archive is ~70MB)
-    let file_size = std::fs::metadata(&download_path)
-        .map(|m| m.len())
-        .unwrap_or(0);
-    if file_size < 1_000_000 {
-        // Probably an error page, not a real binary
-        let content = std::fs::read_to_string(&download_path).unwrap_or_default();
-        let preview = content.chars().take(200).collect::<String>();
-        eprintln!("[openworld] ✗ Downloaded file too small ({} bytes). Content preview: {}", file_size, preview);
-        let _ = std::fs::remove_file(&download_path);
-        return Err(format!("Download corrupted: got {} bytes instead of expected ~70MB", file_size));
-    }
-
-    // Extract if needed (macOS .tgz)
-    let bin_path = get_ollama_bin_path();
-    if needs_extract {
-        eprintln!("[openworld] Extracting archive...");
-        emit_status(app, "downloading", "Extracting AI engine...", Some(0.95));
-
-        let output = Command::new("tar")
-            .args(["xzf", &download_path.to_string_lossy(), "-C", &bin_dir.to_string_lossy()])
-            .output()
-            .map_err(|e| format!("Failed to extract: {}", e))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            eprintln!("[openworld] ✗ tar extraction failed: {}", stderr);
-            return Err(format!("Extraction failed: {}", stderr));
-        }
-
-        // Clean up the archive
-        let _ = std::fs::remove_file(&download_path);
-
-        // The tgz contains 'ollama' binary at the top level (or in a bin/ subfolder)
-        // Check both possibilities
-        if !bin_path.exists() {
-            // Maybe it extracted to a subfolder like bin/ollama
-            let alt_path = bin_dir.join("bin").join("ollama");
-            if alt_path.exists() {
-                std::fs::rename(&alt_path, &bin_path)
-                    .map_err(|e| format!("Failed to move binary: {}", e))?;
-                let _ = std::fs::remove_dir_all(bin_dir.join("bin"));
-            }
-        }
+/// Download and verify the engine archive of `version` (latest if None) into the data
+/// directory. Returns the download, whether it needs extracting and the resolved version.
+async fn download_engine(app: &AppHandle, version: Option<&str>) -> Result<(PathBuf, bool, String), String> {
+    emit_status(app, "downloading", "Downloading AI engine...", Some(0.0));
 
-        if !bin_path.exists() {
-            // List what was actually extracted
-            if let Ok(entries) = std::fs::read_dir(&bin_dir) {
-                eprintln!("[openworld] Files in bin dir after extraction:");
-                for entry in entries.flatten() {
-                    eprintln!("[openworld]   {}", entry.path().display());
-                }
-            }
-            return Err("Extraction succeeded but Ollama binary not found in archive".to_string());
+    let (asset, needs_extract) = get_download_info()?;
+    let client = release_client()?;
+    let (checksums, version) = resolve_release(&client, version).await?;
+    let expected = installer::expected_checksum(&checksums, asset)?;
+    let url = format!("{}/download/{}/{}", installer::RELEASES_BASE_URL, version, asset);
+    eprintln!("[openworld] Downloading Ollama {} from: {} (expecting SHA-256 {})", version, url, expected);
+
+    // Not into bin/: an update moves that directory aside before installing
+    let download_path = installer::download_verified(&client, &url, &expected, &get_data_dir(), |downloaded, total| {
+        if let Some(total) = total.filter(|t| *t > 0) {
+            let pct = downloaded as f64 / total as f64;
+            emit_status(
+                app,
+                "downloading",
+                &format!("Downloading AI engine... {}%", (pct * 100.0) as u32),
+                Some(pct),
+            );
         }
+    })
+    .await?;
+    Ok((download_path, needs_extract, version))
+}
 
-        eprintln!("[openworld] ✓ Extracted binary to: {}", bin_path.display());
-    }
-
-    // Make executable on Unix
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let meta = std::fs::metadata(&bin_path)
-            .map_err(|e| format!("Metadata error: {}", e))?;
-        let mut perms = meta.permissions();
-        perms.set_mode(0o755);
-        std::fs::set_permissions(&bin_path, perms)
-            .map_err(|e| format!("Permission error: {}", e))?;
-    }
+/// Install a verified download into `bin/`, check that it runs and record its version.
+/// If `version` is None (a local archive) it is read from the installed binary.
+fn install_engine(app: &AppHandle, download_path: &Path, needs_extract: bool, version: Option<&str>) -> Result<String, String> {
+    emit_status(app, "downloading", "Extracting AI engine...", Some(0.95));
+    let bin_dir = get_ollama_bin_dir();
+    let bin_path = installer::install_download(download_path, needs_extract, &bin_dir, binary_name())?;
+    eprintln!("[openworld] ✓ Installed binary to: {}", bin_path.display());
 
     // Validate the binary actually runs
     eprintln!("[openworld] Validating binary...");
-    match Command::new(&bin_path).arg("--version").output() {
+    let reported = match Command::new(&bin_path).arg("--version").output() {
         Ok(output) => {
-            let version = String::from_utf8_lossy(&output.stdout);
-            eprintln!("[openworld] ✓ Binary valid: {}", version.trim());
+            let text = format!("{}\n{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+            eprintln!("[openworld] ✓ Binary valid: {}", text.trim());
+            installer::parse_version_output(&text)
         }
         Err(e) => {
             eprintln!("[openworld] ✗ Binary validation failed: {}", e);
             return Err(format!("Downloaded binary is not executable: {}", e));
         }
-    }
+    };
+
+    let root = get_data_dir();
+    let mut manifest = installer::load_manifest(&root);
+    manifest.current = version.map(str::to_string).or(reported);
+    manifest.installed_at = Some(chrono::Utc::now().to_rfc3339());
+    installer::save_manifest(&root, &manifest)?;
 
     emit_status(app, "downloading", "Download complete!", Some(1.0));
     Ok(bin_path.to_string_lossy().to_string())
 }
 
-/// Start `ollama serve` as a background process
+/// Install a user-provided archive through the same pipeline as a download
+fn install_local_archive(app: &AppHandle, archive: &Path) -> Result<String, String> {
+    eprintln!("[openworld] Installing engine from local archive: {}", archive.display());
+    emit_status(app, "downloading", "Installing AI engine from file...", Some(0.0));
+    let copy = installer::prepare_local_archive(archive, &get_data_dir())?;
+    install_engine(app, &copy, true, None)
+}
+
+/// Swap in a new engine while keeping the current one for `rollback_engine`. If the new
+/// engine can't be installed, the current one is put back. The engine must be stopped first.
+fn replace_engine(app: &AppHandle, install: impl FnOnce() -> Result<String, String>) -> Result<(), String> {
+    let root = get_data_dir();
+    let had_engine = get_ollama_bin_path().exists();
+    if had_engine {
+        installer::keep_previous(&root)?;
+    }
+    if let Err(e) = install() {
+        if had_engine {
+            eprintln!("[openworld] ✗ Engine install failed, restoring previous engine: {}", e);
+            installer::swap_with_previous(&root)?;
+            installer::discard_previous(&root)?;
+        }
+        emit_status(app, "error", &format!("Install failed: {}", e), None);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Download the pinned (or latest) Ollama release from GitHub, verify it against the
+/// release's published SHA-256 checksums and install it into `bin/`
+async fn download_ollama(app: &AppHandle) -> Result<String, String> {
+    let (download_path, needs_extract, version) = download_engine(app, pinned_engine_version().as_deref()).await?;
+    install_engine(app, &download_path, needs_extract, Some(&version))
+}
+
+/// Version reported by `ollama --version`. With a server reachable at our host this is
+/// the running engine's version; otherwise it's the version of the binary itself.
+fn engine_version(binary_path: &str) -> Option<String> {
+    let output = Command::new(binary_path)
+        .arg("--version")
+        .env("OLLAMA_HOST", get_ollama_url())
+        .output()
+        .ok()?;
+    let text = format!("{}\n{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    installer::parse_version_output(&text)
+}
+
+/// Start `ollama serve` as a background process, resetting the supervisor's crash backoff
 fn start_ollama_server(binary_path: &str) -> Result<(), String> {
+    spawn_engine(binary_path)?;
+    if let Ok(mut runtime) = ENGINE_RUNTIME.lock() {
+        runtime.consecutive_crashes = 0;
+    }
+    Ok(())
+}
+
+fn spawn_engine(binary_path: &str) -> Result<(), String> {
     eprintln!("[openworld] Starting Ollama server: {} serve", binary_path);
     let mut proc_guard = OLLAMA_PROCESS.lock().map_err(|e| e.to_string())?;
 
@@ -323,21 +378,217 @@ fn start_ollama_server(binary_path: &str) -> Result<(), String> {
         }
     }
 
-    let child = Command::new(binary_path)
+    let config = load_engine_config();
+    let port = resolve_engine_port(&config)?;
+    let mut child = Command::new(binary_path)
         .arg("serve")
-        .stdout(Stdio::inherit())  // Show Ollama output in terminal
-        .stderr(Stdio::inherit())  // Show Ollama errors in terminal
+        .envs(engine_env(&config, port))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
         .spawn()
         .map_err(|e| {
             eprintln!("[openworld] ✗ Failed to spawn Ollama: {}", e);
             format!("Failed to start Ollama: {}", e)
         })?;
+    // Packaged apps have no terminal, so keep the engine's output where the app can show it
+    if let Some(stdout) = child.stdout.take() {
+        engine_logs::capture(stdout, "stdout");
+    }
+    if let Some(stderr) = child.stderr.take() {
+        engine_logs::capture(stderr, "stderr");
+    }
 
-    eprintln!("[openworld] ✓ Ollama process spawned (pid: {})", child.id());
+    eprintln!("[openworld] ✓ Ollama process spawned (pid: {}, port: {})", child.id(), port);
+    if let Err(e) = engine_process::write_pidfile(child.id(), binary_path) {
+        eprintln!("[openworld] {}", e);
+    }
     *proc_guard = Some(child);
+
+    // Everything else talks to the engine through `ollama_host`
+    if port_from_host(&config.ollama_host) != Some(port) {
+        let mut config = load_engine_config();
+        config.ollama_host = format!("http://127.0.0.1:{}", port);
+        save_engine_config(&config)?;
+    }
+    if let Ok(mut runtime) = ENGINE_RUNTIME.lock() {
+        runtime.binary_path = Some(binary_path.to_string());
+        runtime.started_at = Some(Instant::now());
+        runtime.stopped = false;
+    }
     Ok(())
 }
 
+fn port_from_host(host: &str) -> Option<u16> {
+    reqwest::Url::parse(host).ok()?.port_or_known_default()
+}
+
+fn port_is_free(port: u16) -> bool {
+    TcpListener::bind(("127.0.0.1", port)).is_ok()
+}
+
+/// Port to serve on: the configured one (which must be free), else the one in `ollama_host`
+/// if it's free (so the port stays stable across launches), else any free port
+fn resolve_engine_port(config: &EngineConfig) -> Result<u16, String> {
+    if let Some(port) = config.engine_port {
+        if !port_is_free(port) {
+            return Err(format!(
+                "Port {} set for the AI engine is already in use. Choose another port or clear the setting.",
+                port
+            ));
+        }
+        return Ok(port);
+    }
+    let current = port_from_host(&config.ollama_host).unwrap_or(DEFAULT_ENGINE_PORT);
+    if port_is_free(current) {
+        return Ok(current);
+    }
+    let listener = TcpListener::bind(("127.0.0.1", 0)).map_err(|e| format!("No free port for the AI engine: {}", e))?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    eprintln!("[openworld] Port {} is taken, using free port {}", current, port);
+    Ok(port)
+}
+
+/// Environment for `ollama serve` built from the engine settings
+fn engine_env(config: &EngineConfig, port: u16) -> Vec<(&'static str, String)> {
+    let mut env = vec![("OLLAMA_HOST", format!("127.0.0.1:{}", port))];
+    if let Some(dir) = config.engine_models_dir.as_ref().filter(|d| !d.trim().is_empty()) {
+        env.push(("OLLAMA_MODELS", dir.clone()));
+    }
+    if let Some(n) = config.engine_num_parallel {
+        env.push(("OLLAMA_NUM_PARALLEL", n.to_string()));
+    }
+    if let Some(n) = config.engine_max_loaded_models {
+        env.push(("OLLAMA_MAX_LOADED_MODELS", n.to_string()));
+    }
+    if let Some(keep_alive) = config.engine_keep_alive.as_ref().filter(|k| !k.trim().is_empty()) {
+        env.push(("OLLAMA_KEEP_ALIVE", keep_alive.clone()));
+    }
+    if let Some(n) = config.engine_context_length {
+        env.push(("OLLAMA_CONTEXT_LENGTH", n.to_string()));
+    }
+    env
+}
+
+fn restart_delay(attempt: u32) -> Duration {
+    Duration::from_secs(RESTART_BASE_DELAY_SECS << (attempt - 1))
+}
+
+fn describe_exit(status: &std::process::ExitStatus) -> String {
+    if let Some(code) = status.code() {
+        return format!("exit code {}", code);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return format!("killed by signal {}", signal);
+        }
+    }
+    "unknown exit status".to_string()
+}
+
+/// Take the managed process out of its slot if it has exited
+fn reap_exited() -> Option<std::process::ExitStatus> {
+    let mut proc_guard = OLLAMA_PROCESS.lock().ok()?;
+    let status = proc_guard.as_mut()?.try_wait().ok()??;
+    *proc_guard = None;
+    engine_process::remove_pidfile();
+    Some(status)
+}
+
+/// Start the supervisor once per app run. It watches the managed process, reports
+/// when it exits and restarts it with exponential backoff, up to `MAX_RESTARTS` in a row.
+fn start_supervisor(app: &AppHandle) {
+    if !SUPERVISOR_STARTED.swap(true, Ordering::SeqCst) {
+        tauri::async_runtime::spawn(supervise(app.clone()));
+    }
+}
+
+async fn supervise(app: AppHandle) {
+    loop {
+        tokio::time::sleep(Duration::from_millis(SUPERVISOR_POLL_MS)).await;
+        let Some(status) = reap_exited() else {
+            if let Ok(mut runtime) = ENGINE_RUNTIME.lock() {
+                let stable = runtime.started_at.is_some_and(|t| t.elapsed().as_secs() >= STABLE_AFTER_SECS);
+                if stable {
+                    runtime.consecutive_crashes = 0;
+                }
+            }
+            continue;
+        };
+
+        let (binary_path, attempt) = {
+            let Ok(mut runtime) = ENGINE_RUNTIME.lock() else { continue };
+            runtime.last_exit_code = status.code();
+            runtime.started_at = None;
+            if runtime.stopped {
+                continue;
+            }
+            runtime.consecutive_crashes += 1;
+            (runtime.binary_path.clone(), runtime.consecutive_crashes)
+        };
+        let exit = describe_exit(&status);
+        eprintln!("[openworld] ✗ Ollama exited unexpectedly ({})", exit);
+
+        let Some(binary_path) = binary_path.filter(|_| attempt <= MAX_RESTARTS) else {
+            let msg = format!("AI engine stopped ({}) and keeps crashing; gave up after {} restarts.", exit, MAX_RESTARTS);
+            eprintln!("[openworld] ✗ {}", msg);
+            emit_status(&app, "error", &msg, None);
+            continue;
+        };
+        let delay = restart_delay(attempt);
+        emit_status(
+            &app,
+            "error",
+            &format!("AI engine stopped unexpectedly ({}). Restarting in {}s...", exit, delay.as_secs()),
+            None,
+        );
+        tokio::time::sleep(delay).await;
+        if ENGINE_RUNTIME.lock().map(|r| r.stopped).unwrap_or(true) {
+            continue;
+        }
+
+        emit_status(&app, "starting", &format!("Restarting AI engine (attempt {}/{})...", attempt, MAX_RESTARTS), None);
+        if let Err(e) = spawn_engine(&binary_path) {
+            emit_status(&app, "error", &format!("Failed to restart: {}", e), None);
+            continue;
+        }
+        if let Ok(mut runtime) = ENGINE_RUNTIME.lock() {
+            runtime.restart_count += 1;
+        }
+        if wait_for_ready(30).await {
+            emit_ready(&app);
+        } else {
+            emit_status(&app, "error", "AI engine did not come back after restarting.", None);
+        }
+    }
+}
+
+fn managed_pid() -> Option<u32> {
+    OLLAMA_PROCESS.lock().ok().and_then(|guard| guard.as_ref().map(|child| child.id()))
+}
+
+pub async fn get_engine_status() -> EngineStatus {
+    let pid = managed_pid();
+    let owner = match pid {
+        Some(_) => EngineOwner::Managed,
+        None if check_ollama_running().await => EngineOwner::External,
+        None => EngineOwner::None,
+    };
+    let (started_at, restart_count, last_exit_code) = ENGINE_RUNTIME
+        .lock()
+        .map(|r| (r.started_at, r.restart_count, r.last_exit_code))
+        .unwrap_or_default();
+    EngineStatus {
+        owner,
+        running: pid.is_some(),
+        pid,
+        uptime_secs: started_at.filter(|_| pid.is_some()).map(|t| t.elapsed().as_secs()),
+        restart_count,
+        last_exit_code,
+    }
+}
+
 /// Poll Ollama's API until it responds
 async fn wait_for_ready(max_seconds: u32) -> bool {
     let url = format!("{}/api/tags", get_ollama_url());
@@ -370,6 +621,30 @@ fn emit_status(app: &AppHandle, stage: &str, message: &str, progress: Option<f64
             stage: stage.to_string(),
             message: message.to_string(),
             progress,
+            version: None,
+            owner: None,
+        },
+    );
+}
+
+/// Emit the "ready" stage along with the running engine's version
+fn emit_ready(app: &AppHandle) {
+    let version = find_ollama_binary().and_then(|path| engine_version(&path));
+    let owner = if managed_pid().is_some() { EngineOwner::Managed } else { EngineOwner::External };
+    let message = match (owner, &version) {
+        (EngineOwner::Managed, Some(version)) => format!("AI engine ready! (Ollama {})", version),
+        (EngineOwner::Managed, None) => "AI engine ready!".to_string(),
+        (_, Some(version)) => format!("Using the Ollama already running at {} ({})", get_ollama_url(), version),
+        (_, None) => format!("Using the Ollama already running at {}", get_ollama_url()),
+    };
+    let _ = app.emit(
+        "ollama-setup-status",
+        OllamaStatus {
+            stage: "ready".to_string(),
+            message,
+            progress: None,
+            version,
+            owner: Some(owner),
         },
     );
 }
@@ -383,16 +658,19 @@ pub async fn ensure_ollama_ready(app: AppHandle) -> Result<(), String> {
     eprintln!("[openworld] ensure_ollama_ready: starting");
     eprintln!("[openworld] ═══════════════════════════════════════");
 
-    // Step 1: Maybe it's already running
+    // Step 1: Maybe it's already running. Only our own engine counts, unless the user opted
+    // into using an external Ollama; otherwise a system Ollama on the default port would be
+    // used without our engine settings, and ours moves to a free port instead.
     eprintln!("[openworld] Step 1: Check if Ollama is already running...");
     emit_status(&app, "checking", "Checking AI engine...", None);
 
-    if wait_for_ready(3).await {
+    let attach = managed_pid().is_some() || load_engine_config().use_external_engine;
+    if attach && wait_for_ready(3).await {
         eprintln!("[openworld] ✓ Ollama already running!");
-        emit_status(&app, "ready", "AI engine ready!", None);
+        emit_ready(&app);
         return Ok(());
     }
-    eprintln!("[openworld] Ollama not running, need to find/download and start it");
+    eprintln!("[openworld] Managed engine not running, need to find/download and start it");
 
     // Step 2: Find binary
     eprintln!("[openworld] Step 2: Find Ollama binary...");
@@ -403,15 +681,24 @@ pub async fn ensure_ollama_ready(app: AppHandle) -> Result<(), String> {
             path
         }
         None => {
-            eprintln!("[openworld] Binary not found, downloading...");
-            match download_ollama(&app).await {
+            // An archive dropped into offline/ wins over downloading, for air-gapped machines
+            let offline_archive = installer::find_offline_archive(&get_data_dir().join("offline"));
+            let installed = match &offline_archive {
+                Some(archive) => install_local_archive(&app, archive),
+                None => {
+                    eprintln!("[openworld] Binary not found, downloading...");
+                    download_ollama(&app).await
+                }
+            };
+            match installed {
                 Ok(path) => {
-                    eprintln!("[openworld] ✓ Downloaded to: {}", path);
+                    eprintln!("[openworld] ✓ Installed to: {}", path);
                     path
                 }
                 Err(e) => {
-                    eprintln!("[openworld] ✗ Download failed: {}", e);
-                    emit_status(&app, "error", &format!("Download failed: {}", e), None);
+                    let what = if offline_archive.is_some() { "Offline install" } else { "Download" };
+                    eprintln!("[openworld] ✗ {} failed: {}", what, e);
+                    emit_status(&app, "error", &format!("{} failed: {}", what, e), None);
                     return Err(e);
                 }
             }
@@ -426,13 +713,14 @@ pub async fn ensure_ollama_ready(app: AppHandle) -> Result<(), String> {
         emit_status(&app, "error", &format!("Failed to start: {}", e), None);
         return Err(e);
     }
+    start_supervisor(&app);
 
     // Step 4: Wait for it to become responsive
     eprintln!("[openworld] Step 4: Wait for Ollama to be responsive...");
     emit_status(&app, "starting", "Waiting for AI engine to be ready...", None);
     if wait_for_ready(30).await {
         eprintln!("[openworld] ✓ Ollama is ready!");
-        emit_status(&app, "ready", "AI engine ready!", None);
+        emit_ready(&app);
         Ok(())
     } else {
         let msg = "AI engine timed out after 30s. Check terminal for details.".to_string();
@@ -448,7 +736,18 @@ pub async fn check_ollama_running() -> bool {
     client.get(&url).send().await.is_ok()
 }
 
-pub async fn list_installed_models() -> Result<Vec<ModelInfo>, String> {
+/// Read a response body, turning a non-2xx status into an `OllamaError`
+async fn response_body(resp: reqwest::Response, model: Option<&str>) -> Result<String, OllamaError> {
+    let status = resp.status();
+    let body = resp.text().await.map_err(OllamaError::from_request)?;
+    if !status.is_success() {
+        eprintln!("[openworld] Ollama returned HTTP {}: {}", status, &body[..body.len().min(500)]);
+        return Err(OllamaError::from_response(status.as_u16(), &body, model));
+    }
+    Ok(body)
+}
+
+pub async fn list_installed_models() -> Result<Vec<ModelInfo>, OllamaError> {
     let url = format!("{}/api/tags", get_ollama_url());
     eprintln!("[openworld] list_installed_models: GET {}", url);
     let client = Client::new();
@@ -457,15 +756,17 @@ pub async fn list_installed_models() -> Result<Vec<ModelInfo>, String> {
         .get(&url)
         .send()
         .await
-        .map_err(|e| { eprintln!("[openworld] list_models failed: {}", e); format!("Failed to connect to Ollama: {}", e) })?;
+        .map_err(|e| { eprintln!("[openworld] list_models failed: {}", e); OllamaError::from_request(e) })?;
 
     eprintln!("[openworld] list_models response: HTTP {}", resp.status());
 
-    let body = resp.text().await.map_err(|e| format!("Failed to read response: {}", e))?;
+    let body = response_body(resp, None).await?;
     eprintln!("[openworld] list_models body: {}", &body[..body.len().min(500)]);
 
-    let tags: TagsResponse = serde_json::from_str(&body)
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let tags: TagsResponse = serde_json::from_str(&body).map_err(|e| OllamaError::Other {
+        status: None,
+        detail: format!("Failed to parse response: {}", e),
+    })?;
 
     let models: Vec<ModelInfo> = tags
         .models
@@ -493,23 +794,31 @@ pub async fn list_installed_models() -> Result<Vec<ModelInfo>, String> {
     Ok(models)
 }
 
-pub async fn pull_model(app: AppHandle, model_name: String) -> Result<(), String> {
+/// Stream `/api/pull` for `model_name`, calling `on_progress` for every status line.
+/// Returns `Ok(false)` if `cancel` is notified before the pull finishes. Ollama keeps
+/// the layers downloaded so far, so pulling the same model again resumes it.
+pub async fn pull_model(model_name: &str, cancel: &Notify, mut on_progress: impl FnMut(&PullProgress)) -> Result<bool, OllamaError> {
     eprintln!("[openworld] pull_model: pulling '{}'", model_name);
 
     // Make sure Ollama is running before pulling
     if !check_ollama_running().await {
         eprintln!("[openworld] pull_model: Ollama not running!");
-        return Err("AI engine is not running. Please restart the app.".to_string());
+        return Err(OllamaError::EngineNotRunning);
     }
     eprintln!("[openworld] pull_model: Ollama is running, starting pull...");
 
     let url = format!("{}/api/pull", get_ollama_url());
+    // No overall timeout, large models take hours on slow links; only a stalled connection fails
     let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(3600)) // 1 hour for large models
+        .connect_timeout(std::time::Duration::from_secs(30))
+        .read_timeout(std::time::Duration::from_secs(300))
         .build()
-        .map_err(|e| format!("HTTP client error: {}", e))?;
+        .map_err(|e| OllamaError::Other {
+            status: None,
+            detail: format!("HTTP client error: {}", e),
+        })?;
 
-    let resp = client
+    let mut resp = client
         .post(&url)
         .json(&serde_json::json!({
             "name": model_name,
@@ -517,59 +826,243 @@ pub async fn pull_model(app: AppHandle, model_name: String) -> Result<(), String
         }))
         .send()
         .await
-        .map_err(|e| format!("Failed to start model pull: {}", e))?;
+        .map_err(OllamaError::from_request)?;
+    if !resp.status().is_success() {
+        return Err(response_body(resp, Some(model_name)).await.err().unwrap_or(OllamaError::Other {
+            status: None,
+            detail: "Pull failed".to_string(),
+        }));
+    }
 
-    let mut stream = resp;
     let mut buffer = Vec::new();
+    let mut handle_line = |line: &[u8]| -> Result<(), OllamaError> {
+        let line_str = String::from_utf8_lossy(line);
+        if let Ok(progress) = serde_json::from_str::<PullProgress>(&line_str) {
+            // Failures after the stream has started arrive as `{"error": "..."}` lines
+            if let Some(error) = &progress.error {
+                eprintln!("[openworld] pull_model: '{}' failed: {}", model_name, error);
+                return Err(OllamaError::classify(None, error, Some(model_name)));
+            }
+            on_progress(&progress);
+        }
+        Ok(())
+    };
 
-    while let Ok(chunk) = stream.chunk().await {
-        match chunk {
-            Some(bytes) => {
-                buffer.extend_from_slice(&bytes);
-                while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
-                    let line: Vec<u8> = buffer.drain(..=pos).collect();
-                    let line_str = String::from_utf8_lossy(&line);
-                    if let Ok(progress) = serde_json::from_str::<PullProgress>(&line_str) {
-                        let _ = app.emit("model-pull-progress", &progress);
-                    }
-                }
+    loop {
+        let chunk = tokio::select! {
+            chunk = resp.chunk() => chunk.map_err(OllamaError::from_request)?,
+            _ = cancel.notified() => {
+                eprintln!("[openworld] pull_model: '{}' cancelled", model_name);
+                return Ok(false);
             }
-            None => break,
+        };
+        let Some(bytes) = chunk else { break };
+        buffer.extend_from_slice(&bytes);
+        while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=pos).collect();
+            handle_line(&line)?;
         }
     }
 
     if !buffer.is_empty() {
-        let line_str = String::from_utf8_lossy(&buffer);
-        if let Ok(progress) = serde_json::from_str::<PullProgress>(&line_str) {
-            let _ = app.emit("model-pull-progress", &progress);
-        }
+        handle_line(&buffer)?;
     }
 
-    Ok(())
+    Ok(true)
 }
 
-pub async fn delete_model(model_name: &str) -> Result<(), String> {
+pub async fn delete_model(model_name: &str) -> Result<(), OllamaError> {
     let url = format!("{}/api/delete", get_ollama_url());
     let client = Client::new();
 
-    client
+    let resp = client
         .delete(&url)
         .json(&serde_json::json!({
             "name": model_name
         }))
         .send()
         .await
-        .map_err(|e| format!("Failed to delete model: {}", e))?;
+        .map_err(OllamaError::from_request)?;
+    response_body(resp, Some(model_name)).await?;
 
     Ok(())
 }
 
-/// Stop the managed Ollama process on app exit
+/// Compare the bundled engine with the pinned version, or the latest release if none is pinned
+pub async fn check_engine_update() -> Result<EngineUpdateInfo, String> {
+    let pinned = pinned_engine_version();
+    let (_, available) = resolve_release(&release_client()?, pinned.as_deref()).await?;
+    let manifest = installer::load_manifest(&get_data_dir());
+    Ok(EngineUpdateInfo {
+        update_available: manifest.current.as_deref() != Some(available.as_str()),
+        installed: manifest.current,
+        available,
+        pinned: pinned.is_some(),
+        previous: manifest.previous,
+    })
+}
+
+/// Install the pinned (or latest) engine, keeping the current one for `rollback_engine`.
+/// If the new engine can't be installed the current one is put back. Returns the installed version.
+pub async fn update_engine(app: AppHandle) -> Result<String, String> {
+    let root = get_data_dir();
+    let manifest = installer::load_manifest(&root);
+    let (download_path, needs_extract, version) = download_engine(&app, pinned_engine_version().as_deref()).await?;
+    if manifest.current.as_deref() == Some(version.as_str()) && get_ollama_bin_path().exists() {
+        let _ = std::fs::remove_file(&download_path);
+        emit_ready(&app);
+        return Ok(version);
+    }
+
+    eprintln!("[openworld] Updating engine {:?} -> {}", manifest.current, version);
+    stop_engine().await;
+    if let Err(e) = replace_engine(&app, || install_engine(&app, &download_path, needs_extract, Some(&version))) {
+        let _ = ensure_ollama_ready(app).await;
+        return Err(e);
+    }
+    ensure_ollama_ready(app).await?;
+    Ok(version)
+}
+
+/// Install the engine from an Ollama release archive on disk (for machines without
+/// internet access), keeping the current one for `rollback_engine`. Returns the installed version.
+pub async fn install_engine_from_file(app: AppHandle, path: String) -> Result<Option<String>, String> {
+    stop_engine().await;
+    if let Err(e) = replace_engine(&app, || install_local_archive(&app, Path::new(&path))) {
+        let _ = ensure_ollama_ready(app).await;
+        return Err(e);
+    }
+    ensure_ollama_ready(app).await?;
+    Ok(installer::load_manifest(&get_data_dir()).current)
+}
+
+/// Switch back to the engine that the last update replaced. Returns the now-current version.
+pub async fn rollback_engine(app: AppHandle) -> Result<Option<String>, String> {
+    let root = get_data_dir();
+    // Checked before stopping, so a failed rollback doesn't leave the engine down
+    if !installer::has_previous(&root) {
+        return Err("No previous engine version to roll back to".to_string());
+    }
+    stop_engine().await;
+    if let Err(e) = installer::swap_with_previous(&root) {
+        let _ = ensure_ollama_ready(app).await;
+        return Err(e);
+    }
+    let current = installer::load_manifest(&root).current;
+    eprintln!("[openworld] Rolled engine back to {:?}", current);
+    ensure_ollama_ready(app).await?;
+    Ok(current)
+}
+
+/// Engine settings as entered on the settings page, trimmed and checked before saving
+fn normalize_engine_config(mut config: EngineConfig) -> Result<EngineConfig, String> {
+    let host = config.ollama_host.trim().trim_end_matches('/');
+    config.ollama_host = if host.is_empty() { EngineConfig::default().ollama_host } else { host.to_string() };
+    if port_from_host(&config.ollama_host).is_none() {
+        return Err(format!("Not a valid AI engine address: {}", config.ollama_host));
+    }
+    if config.engine_port == Some(0) {
+        return Err("The AI engine port must be between 1 and 65535".to_string());
+    }
+    let non_blank = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    config.engine_models_dir = non_blank(config.engine_models_dir);
+    config.engine_keep_alive = non_blank(config.engine_keep_alive);
+    config.pinned_engine_version = non_blank(config.pinned_engine_version).map(|v| installer::normalize_version(&v));
+    Ok(config)
+}
+
+/// Save the engine settings and restart the managed engine so its environment picks them up
+pub async fn apply_engine_config(app: AppHandle, config: EngineConfig) -> Result<EngineConfig, String> {
+    let config = normalize_engine_config(config)?;
+    save_engine_config(&config)?;
+    stop_engine().await;
+    ensure_ollama_ready(app).await?;
+    // Starting may have moved the engine to another port
+    Ok(load_engine_config())
+}
+
+/// Stop the managed Ollama process, giving it `STOP_TIMEOUT` to exit after SIGTERM.
+/// The process is taken out of its slot first, so the lock isn't held while waiting.
 pub fn stop_ollama() {
-    if let Ok(mut proc_guard) = OLLAMA_PROCESS.lock() {
-        if let Some(ref mut child) = *proc_guard {
-            let _ = child.kill();
-        }
-        *proc_guard = None;
+    if let Ok(mut runtime) = ENGINE_RUNTIME.lock() {
+        runtime.stopped = true;
+        runtime.started_at = None;
+    }
+    let child = OLLAMA_PROCESS.lock().ok().and_then(|mut proc_guard| proc_guard.take());
+    if let Some(mut child) = child {
+        engine_process::terminate_child(&mut child, engine_process::STOP_TIMEOUT);
+        engine_process::remove_pidfile();
+    }
+}
+
+/// `stop_ollama` for async callers, run on the blocking pool since it can wait for seconds
+async fn stop_engine() {
+    let _ = tauri::async_runtime::spawn_blocking(stop_ollama).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restart_delay_doubles() {
+        let delays: Vec<u64> = (1..=MAX_RESTARTS).map(|a| restart_delay(a).as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16]);
+    }
+
+    #[test]
+    fn test_normalize_engine_config() {
+        let config = normalize_engine_config(EngineConfig {
+            ollama_host: " http://127.0.0.1:11500/ ".to_string(),
+            engine_models_dir: Some("  ".to_string()),
+            engine_keep_alive: Some(" 10m ".to_string()),
+            pinned_engine_version: Some(" 0.5.7".to_string()),
+            ..EngineConfig::default()
+        })
+        .unwrap();
+        assert_eq!(config.pinned_engine_version.as_deref(), Some("v0.5.7"));
+        assert_eq!(config.ollama_host, "http://127.0.0.1:11500");
+        assert_eq!(config.engine_models_dir, None);
+        assert_eq!(config.engine_keep_alive.as_deref(), Some("10m"));
+
+        let blank = normalize_engine_config(EngineConfig { ollama_host: String::new(), ..EngineConfig::default() }).unwrap();
+        assert_eq!(blank.ollama_host, EngineConfig::default().ollama_host);
+        assert!(normalize_engine_config(EngineConfig { ollama_host: "not a url".to_string(), ..EngineConfig::default() }).is_err());
+        assert!(normalize_engine_config(EngineConfig { engine_port: Some(0), ..EngineConfig::default() }).is_err());
+    }
+
+    #[test]
+    fn test_engine_env_from_config() {
+        let config = EngineConfig {
+            engine_models_dir: Some("/mnt/models".to_string()),
+            engine_num_parallel: Some(2),
+            engine_keep_alive: Some(" ".to_string()),
+            engine_context_length: Some(8192),
+            ..EngineConfig::default()
+        };
+        assert_eq!(
+            engine_env(&config, 11500),
+            vec![
+                ("OLLAMA_HOST", "127.0.0.1:11500".to_string()),
+                ("OLLAMA_MODELS", "/mnt/models".to_string()),
+                ("OLLAMA_NUM_PARALLEL", "2".to_string()),
+                ("OLLAMA_CONTEXT_LENGTH", "8192".to_string()),
+            ]
+        );
+        assert_eq!(port_from_host("http://localhost:11434"), Some(11434));
+        assert_eq!(port_from_host("http://127.0.0.1:5000/"), Some(5000));
+    }
+
+    #[test]
+    fn test_resolve_engine_port_avoids_taken_port() {
+        let taken = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let taken_port = taken.local_addr().unwrap().port();
+        let config = EngineConfig {
+            ollama_host: format!("http://127.0.0.1:{}", taken_port),
+            ..EngineConfig::default()
+        };
+        assert_ne!(resolve_engine_port(&config).unwrap(), taken_port);
+        let pinned = EngineConfig { engine_port: Some(taken_port), ..config };
+        assert!(resolve_engine_port(&pinned).unwrap_err().contains("already in use"));
     }
 }
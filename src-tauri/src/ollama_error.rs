@@ -0,0 +1,167 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+/// A failed Ollama API call, classified so the frontend can tell the user what to do.
+/// Serialized as `{ "kind": "not_found", "message": "..." }`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OllamaError {
+    /// The model (or tag) doesn't exist locally or in the registry
+    NotFound { model: Option<String> },
+    DiskFull,
+    /// Ollama couldn't reach the registry, or the connection dropped
+    Network { detail: String },
+    /// The registry refused the request (private model, missing sign-in)
+    UnauthorizedRegistry { detail: String },
+    /// Nothing is answering at `ollama_host`
+    EngineNotRunning,
+    Other { status: Option<u16>, detail: String },
+}
+
+impl OllamaError {
+    /// Classify an error reported by Ollama, from its HTTP status (if any) and the
+    /// message of its `{"error": "..."}` payload or stream line
+    pub fn classify(status: Option<u16>, message: &str, model: Option<&str>) -> Self {
+        let lower = message.to_lowercase();
+        let has = |needles: &[&str]| needles.iter().any(|n| lower.contains(n));
+
+        if has(&["no space left on device", "disk full", "not enough space", "insufficient disk"]) {
+            return OllamaError::DiskFull;
+        }
+        if matches!(status, Some(401) | Some(403)) || has(&["unauthorized", "authentication required", "access denied", "forbidden"]) {
+            return OllamaError::UnauthorizedRegistry { detail: message.to_string() };
+        }
+        if status == Some(404) || has(&["not found", "file does not exist", "manifest unknown"]) {
+            return OllamaError::NotFound { model: model.map(str::to_string) };
+        }
+        if has(&[
+            "dial tcp",
+            "no such host",
+            "connection refused",
+            "connection reset",
+            "i/o timeout",
+            "tls handshake",
+            "network is unreachable",
+            "temporary failure in name resolution",
+            "max retries exceeded",
+        ]) {
+            return OllamaError::Network { detail: message.to_string() };
+        }
+        OllamaError::Other {
+            status,
+            detail: message.to_string(),
+        }
+    }
+
+    /// Classify a non-2xx response body, which Ollama sends as `{"error": "..."}`
+    pub fn from_response(status: u16, body: &str, model: Option<&str>) -> Self {
+        Self::classify(Some(status), &error_message(body), model)
+    }
+
+    /// A request to the local engine that never got a response
+    pub fn from_request(e: reqwest::Error) -> Self {
+        if e.is_connect() {
+            OllamaError::EngineNotRunning
+        } else {
+            OllamaError::Network { detail: e.to_string() }
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OllamaError::NotFound { .. } => "not_found",
+            OllamaError::DiskFull => "disk_full",
+            OllamaError::Network { .. } => "network",
+            OllamaError::UnauthorizedRegistry { .. } => "unauthorized_registry",
+            OllamaError::EngineNotRunning => "engine_not_running",
+            OllamaError::Other { .. } => "other",
+        }
+    }
+}
+
+/// The `error` field of an Ollama error payload, or the raw body if it isn't one
+pub fn error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(str::to_string))
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+impl fmt::Display for OllamaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OllamaError::NotFound { model: Some(model) } => write!(
+                f,
+                "Model \"{}\" was not found. Check the name and tag (e.g. llama3:8b) in the Ollama library.",
+                model
+            ),
+            OllamaError::NotFound { model: None } => {
+                write!(f, "Model not found. Check the name and tag (e.g. llama3:8b) in the Ollama library.")
+            }
+            OllamaError::DiskFull => write!(
+                f,
+                "Not enough disk space. Free up space, delete unused models or choose a smaller model, then try again."
+            ),
+            OllamaError::Network { detail } => write!(
+                f,
+                "Couldn't reach the model registry. Check your internet connection, proxy or firewall and try again. ({})",
+                detail
+            ),
+            OllamaError::UnauthorizedRegistry { detail } => write!(
+                f,
+                "The model registry refused access. The model may be private or require signing in. ({})",
+                detail
+            ),
+            OllamaError::EngineNotRunning => {
+                write!(f, "AI engine is not running. Wait a moment, or restart the app if this persists.")
+            }
+            OllamaError::Other { status: Some(status), detail } => write!(f, "Ollama error (HTTP {}): {}", status, detail),
+            OllamaError::Other { status: None, detail } => write!(f, "Ollama error: {}", detail),
+        }
+    }
+}
+
+impl From<OllamaError> for String {
+    fn from(e: OllamaError) -> Self {
+        e.to_string()
+    }
+}
+
+impl Serialize for OllamaError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("OllamaError", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_ollama_errors() {
+        let cases = [
+            (None, "pull model manifest: file does not exist", "not_found"),
+            (Some(404), "model 'lama3' not found", "not_found"),
+            (None, "write /root/.ollama/models/blobs/sha256-partial: no space left on device", "disk_full"),
+            (Some(401), "pull model manifest: 401: unauthorized", "unauthorized_registry"),
+            (None, "pull model manifest: Get \"https://registry.ollama.ai/v2/...\": dial tcp: lookup registry.ollama.ai: no such host", "network"),
+            (Some(500), "llama runner process has terminated", "other"),
+        ];
+        for (status, message, kind) in cases {
+            assert_eq!(OllamaError::classify(status, message, Some("lama3")).kind(), kind, "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_error_payload_and_serialization() {
+        let err = OllamaError::from_response(404, r#"{"error":"model 'lama3' not found"}"#, Some("lama3"));
+        assert_eq!(err, OllamaError::NotFound { model: Some("lama3".to_string()) });
+        assert_eq!(error_message("upstream timeout\n"), "upstream timeout");
+
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["kind"], "not_found");
+        assert!(json["message"].as_str().unwrap().contains("\"lama3\" was not found"));
+    }
+}
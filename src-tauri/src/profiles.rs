@@ -0,0 +1,182 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::config::{get_data_dir, load_config, save_config_to, AppConfig};
+use crate::crypto;
+use crate::storage::StorageEngine;
+
+/// The profile that existed before profiles were introduced; its data stays at the top of the data directory
+pub const DEFAULT_PROFILE_ID: &str = "default";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+    /// The profile's key is derived from a passphrase instead of a keyfile
+    #[serde(default)]
+    pub uses_passphrase: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProfileRegistry {
+    active: String,
+    profiles: Vec<Profile>,
+}
+
+impl Default for ProfileRegistry {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_PROFILE_ID.to_string(),
+            profiles: vec![Profile {
+                id: DEFAULT_PROFILE_ID.to_string(),
+                name: "Default".to_string(),
+                created_at: Utc::now().to_rfc3339(),
+                uses_passphrase: false,
+            }],
+        }
+    }
+}
+
+fn registry_path(root: &Path) -> PathBuf {
+    root.join("profiles.json")
+}
+
+fn load_registry(root: &Path) -> ProfileRegistry {
+    fs::read_to_string(registry_path(root))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_registry(root: &Path, registry: &ProfileRegistry) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(registry)
+        .map_err(|e| format!("Failed to serialize profiles: {}", e))?;
+    fs::write(registry_path(root), json).map_err(|e| format!("Failed to write profiles: {}", e))
+}
+
+fn profile_dir_in(root: &Path, id: &str) -> PathBuf {
+    if id == DEFAULT_PROFILE_ID {
+        root.to_path_buf()
+    } else {
+        root.join("profiles").join(id)
+    }
+}
+
+/// Directory holding a profile's `data.db`, key material and `config.json`
+pub fn profile_dir(id: &str) -> PathBuf {
+    profile_dir_in(&get_data_dir(), id)
+}
+
+pub fn list_profiles() -> Vec<Profile> {
+    load_registry(&get_data_dir()).profiles
+}
+
+pub fn get_profile(id: &str) -> Result<Profile, String> {
+    list_profiles()
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("Profile not found: {}", id))
+}
+
+/// Create a profile directory with its own key material and config. Engine-wide
+/// settings are copied from the active profile so the new one doesn't need setup again.
+pub fn create_profile(name: &str, passphrase: Option<&str>) -> Result<Profile, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Profile name cannot be empty".to_string());
+    }
+    if passphrase == Some("") {
+        return Err("Passphrase cannot be empty".to_string());
+    }
+    let root = get_data_dir();
+    let mut registry = load_registry(&root);
+    if registry.profiles.iter().any(|p| p.name.eq_ignore_ascii_case(name)) {
+        return Err(format!("A profile named \"{}\" already exists", name));
+    }
+
+    let profile = Profile {
+        id: Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        uses_passphrase: passphrase.is_some(),
+    };
+    let dir = profile_dir_in(&root, &profile.id);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create profile directory: {}", e))?;
+
+    match passphrase {
+        Some(passphrase) => crypto::get_or_create_passphrase_secret(&dir, passphrase)?,
+        None => crypto::get_or_create_master_secret(&dir)?,
+    };
+
+    let current = load_config();
+    let config = AppConfig {
+        default_model: current.default_model,
+        setup_complete: current.setup_complete,
+        theme: current.theme,
+        ..AppConfig::default()
+    };
+    save_config_to(&dir.join("config.json"), &config)?;
+
+    registry.profiles.push(profile.clone());
+    save_registry(&root, &registry)?;
+    Ok(profile)
+}
+
+/// Open a profile's database, unlocking it with `passphrase` if it needs one
+pub fn open_profile(profile: &Profile, passphrase: Option<&str>) -> Result<StorageEngine, String> {
+    let dir = profile_dir(&profile.id);
+    if profile.uses_passphrase {
+        let passphrase = passphrase.ok_or("This profile is locked with a passphrase")?;
+        let secret = crypto::get_or_create_passphrase_secret(&dir, passphrase)?;
+        StorageEngine::open_with_secret(&dir, &secret)
+    } else {
+        StorageEngine::open(&dir)
+    }
+}
+
+/// Remember `id` as the profile to open on the next launch
+pub fn set_active_profile(id: &str) -> Result<(), String> {
+    let root = get_data_dir();
+    let mut registry = load_registry(&root);
+    registry.active = id.to_string();
+    save_registry(&root, &registry)
+}
+
+/// Profile to open at launch. Passphrase profiles can't be unlocked without the
+/// user, so the app starts in the default profile instead.
+pub fn startup_profile() -> Profile {
+    let registry = load_registry(&get_data_dir());
+    let default = registry.profiles.iter().find(|p| p.id == DEFAULT_PROFILE_ID).cloned();
+    registry
+        .profiles
+        .into_iter()
+        .find(|p| p.id == registry.active && !p.uses_passphrase)
+        .or(default)
+        .unwrap_or_else(|| ProfileRegistry::default().profiles.remove(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_dirs() {
+        let root = Path::new("/data");
+        assert_eq!(profile_dir_in(root, DEFAULT_PROFILE_ID), PathBuf::from("/data"));
+        assert_eq!(profile_dir_in(root, "abc"), PathBuf::from("/data/profiles/abc"));
+    }
+
+    #[test]
+    fn test_registry_defaults_to_default_profile() {
+        let root = std::env::temp_dir().join(format!("openworld-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        let registry = load_registry(&root);
+        assert_eq!(registry.active, DEFAULT_PROFILE_ID);
+        assert_eq!(registry.profiles.len(), 1);
+        let _ = fs::remove_dir_all(root);
+    }
+}
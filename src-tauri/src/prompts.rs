@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::chat::GenerationOptions;
+use crate::storage::{PromptTemplateDraft, StorageEngine};
+
+pub const PROMPT_LIBRARY_FORMAT_ID: &str = "openworld-prompts";
+pub const PROMPT_LIBRARY_VERSION: u32 = 1;
+
+/// A template filled in and ready to be sent with `send_message`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderedPrompt {
+    pub content: String,
+    pub model: Option<String>,
+    pub options: GenerationOptions,
+}
+
+/// JSON document used to move a prompt library between installs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptLibrary {
+    pub format: String,
+    pub version: u32,
+    pub templates: Vec<PromptTemplateDraft>,
+}
+
+/// Result of importing a prompt library file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptImportSummary {
+    pub imported: usize,
+    /// Templates whose name already exists (or repeats earlier in the file)
+    pub skipped_duplicates: usize,
+    /// Templates that couldn't be saved (e.g. no name), with the reason
+    pub errors: Vec<String>,
+}
+
+/// Split a template into literal text and `{{variable}}` names. Braces that don't
+/// form a valid placeholder are kept as literal text.
+fn tokenize(content: &str) -> Vec<(bool, &str)> {
+    let mut tokens = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else { break };
+        let name = after[..end].trim();
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+        if valid {
+            tokens.push((false, &rest[..start]));
+            tokens.push((true, name));
+        } else {
+            tokens.push((false, &rest[..start + 2 + end + 2]));
+        }
+        rest = &after[end + 2..];
+    }
+    tokens.push((false, rest));
+    tokens
+}
+
+/// Variable names used in a template, in order of first appearance
+pub fn template_variables(content: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for (is_var, text) in tokenize(content) {
+        if is_var && !names.iter().any(|n| n == text) {
+            names.push(text.to_string());
+        }
+    }
+    names
+}
+
+/// Fill in every placeholder, failing with the list of missing variables if any are unset
+pub fn render(content: &str, vars: &HashMap<String, String>) -> Result<String, String> {
+    let missing: Vec<String> = template_variables(content)
+        .into_iter()
+        .filter(|name| !vars.contains_key(name))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("Missing template variables: {}", missing.join(", ")));
+    }
+
+    Ok(tokenize(content)
+        .into_iter()
+        .map(|(is_var, text)| if is_var { vars[text].as_str() } else { text })
+        .collect())
+}
+
+pub fn render_template(
+    storage: &StorageEngine,
+    template_id: &str,
+    vars: &HashMap<String, String>,
+) -> Result<RenderedPrompt, String> {
+    let template = storage
+        .get_prompt_template(template_id)?
+        .ok_or_else(|| format!("Prompt template not found: {}", template_id))?;
+    Ok(RenderedPrompt {
+        content: render(&template.content, vars)?,
+        model: template.default_model,
+        options: template.options,
+    })
+}
+
+pub fn export_library(storage: &StorageEngine) -> Result<String, String> {
+    let library = PromptLibrary {
+        format: PROMPT_LIBRARY_FORMAT_ID.to_string(),
+        version: PROMPT_LIBRARY_VERSION,
+        templates: storage
+            .list_prompt_templates()?
+            .into_iter()
+            .map(|t| PromptTemplateDraft {
+                name: t.name,
+                content: t.content,
+                default_model: t.default_model,
+                options: t.options,
+            })
+            .collect(),
+    };
+    serde_json::to_string_pretty(&library).map_err(|e| format!("Failed to serialize prompt library: {}", e))
+}
+
+/// Import templates from a library file, skipping names that already exist and
+/// templates that can't be saved
+pub fn import_library_file(storage: &StorageEngine, path: &str) -> Result<PromptImportSummary, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    import_library(storage, &content)
+}
+
+pub fn import_library(storage: &StorageEngine, content: &str) -> Result<PromptImportSummary, String> {
+    let library: PromptLibrary =
+        serde_json::from_str(content).map_err(|e| format!("Not a prompt library file: {}", e))?;
+    if library.format != PROMPT_LIBRARY_FORMAT_ID {
+        return Err(format!("Unsupported prompt library format: {}", library.format));
+    }
+    if library.version > PROMPT_LIBRARY_VERSION {
+        return Err(format!("Prompt library version {} is newer than this app supports", library.version));
+    }
+
+    let mut existing: HashSet<String> = storage
+        .list_prompt_templates()?
+        .into_iter()
+        .map(|t| t.name.trim().to_lowercase())
+        .collect();
+    let mut summary = PromptImportSummary::default();
+    for (i, draft) in library.templates.iter().enumerate() {
+        let name = draft.name.trim().to_lowercase();
+        if existing.contains(&name) {
+            summary.skipped_duplicates += 1;
+            continue;
+        }
+        match storage.create_prompt_template(draft) {
+            Ok(_) => {
+                summary.imported += 1;
+                existing.insert(name);
+            }
+            Err(e) => summary.errors.push(format!("Template {}: {}", i + 1, e)),
+        }
+    }
+
+    eprintln!(
+        "[openworld] Prompt import: {} imported, {} duplicate(s), {} error(s)",
+        summary.imported,
+        summary.skipped_duplicates,
+        summary.errors.len()
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::TempStorage;
+
+    #[test]
+    fn test_template_variables() {
+        let vars = template_variables("Review {{ diff }} for {{lang}}, then {{diff}} again. Keep {{ }} and {{not valid}}.");
+        assert_eq!(vars, vec!["diff".to_string(), "lang".to_string()]);
+    }
+
+    #[test]
+    fn test_render_fills_and_validates() {
+        let mut vars = HashMap::new();
+        vars.insert("lang".to_string(), "Rust".to_string());
+        assert_eq!(
+            render("Write {{lang}} code for {{ task }}", &vars),
+            Err("Missing template variables: task".to_string())
+        );
+
+        vars.insert("task".to_string(), "parsing {{braces}}".to_string());
+        assert_eq!(
+            render("Write {{lang}} code for {{ task }} {x}", &vars).unwrap(),
+            "Write Rust code for parsing {{braces}} {x}"
+        );
+    }
+
+    #[test]
+    fn test_import_skips_duplicates_and_invalid() {
+        let storage = TempStorage::new();
+        let draft = |name: &str| PromptTemplateDraft {
+            name: name.to_string(),
+            content: "Summarize {{text}}".to_string(),
+            default_model: None,
+            options: GenerationOptions::default(),
+        };
+        storage.create_prompt_template(&draft("Summarize")).unwrap();
+
+        let library = PromptLibrary {
+            format: PROMPT_LIBRARY_FORMAT_ID.to_string(),
+            version: PROMPT_LIBRARY_VERSION,
+            templates: vec![draft("summarize "), draft("Translate"), draft("  "), draft("translate"), draft("Review")],
+        };
+        let summary = import_library(&storage, &serde_json::to_string(&library).unwrap()).unwrap();
+        assert_eq!(summary.imported, 2);
+        assert_eq!(summary.skipped_duplicates, 2);
+        assert_eq!(summary.errors.len(), 1);
+        let names: Vec<String> = storage.list_prompt_templates().unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["Review", "Summarize", "Translate"]);
+    }
+}
@@ -0,0 +1,366 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::config::get_data_dir;
+use crate::ollama::{self, PullProgress};
+use crate::ollama_error::OllamaError;
+
+/// Download speed is averaged over this much recent progress
+const SPEED_WINDOW: Duration = Duration::from_secs(5);
+/// How long a pull waits for the engine to come up (e.g. right after launch) before failing
+const ENGINE_WAIT_SECS: u64 = 120;
+const ENGINE_POLL_SECS: u64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PullState {
+    Queued,
+    Downloading,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl PullState {
+    fn is_finished(self) -> bool {
+        matches!(self, PullState::Completed | PullState::Failed | PullState::Cancelled)
+    }
+}
+
+/// One model pull, sent as the payload of `model-pull-progress` and `model-pull-finished`
+#[derive(Debug, Clone, Serialize)]
+pub struct PullInfo {
+    pub id: String,
+    pub model: String,
+    pub state: PullState,
+    /// Latest status line from Ollama ("pulling manifest", "verifying sha256 digest", ...)
+    pub status: String,
+    /// Bytes downloaded and expected, summed over all layers seen so far
+    pub completed: Option<u64>,
+    pub total: Option<u64>,
+    /// 0–100
+    pub percent: Option<f64>,
+    pub bytes_per_sec: Option<f64>,
+    pub eta_secs: Option<u64>,
+    pub error: Option<OllamaError>,
+}
+
+impl PullInfo {
+    fn new(id: String, model: String) -> Self {
+        Self {
+            id,
+            model,
+            state: PullState::Queued,
+            status: "Queued".to_string(),
+            completed: None,
+            total: None,
+            percent: None,
+            bytes_per_sec: None,
+            eta_secs: None,
+            error: None,
+        }
+    }
+}
+
+/// Turns Ollama's per-layer progress lines into overall progress, speed and ETA
+#[derive(Default)]
+struct PullTracker {
+    /// digest -> (completed, total)
+    layers: HashMap<String, (u64, u64)>,
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl PullTracker {
+    fn update(&mut self, progress: &PullProgress, now: Instant, info: &mut PullInfo) {
+        info.status = progress.status.clone();
+        let (Some(digest), Some(total)) = (&progress.digest, progress.total) else { return };
+        let completed = progress.completed.unwrap_or(0).min(total);
+        self.layers.insert(digest.clone(), (completed, total));
+
+        let completed: u64 = self.layers.values().map(|(c, _)| c).sum();
+        let total: u64 = self.layers.values().map(|(_, t)| t).sum();
+        self.samples.push_back((now, completed));
+        while self.samples.len() > 2 && self.samples.front().is_some_and(|(t, _)| now.duration_since(*t) > SPEED_WINDOW) {
+            self.samples.pop_front();
+        }
+
+        info.completed = Some(completed);
+        info.total = Some(total);
+        info.percent = (total > 0).then(|| completed as f64 * 100.0 / total as f64);
+        info.bytes_per_sec = self.samples.front().and_then(|(start, start_bytes)| {
+            let elapsed = now.duration_since(*start).as_secs_f64();
+            (elapsed > 0.0).then(|| completed.saturating_sub(*start_bytes) as f64 / elapsed)
+        });
+        info.eta_secs = info
+            .bytes_per_sec
+            .filter(|speed| *speed > 0.0)
+            .map(|speed| (total.saturating_sub(completed) as f64 / speed).ceil() as u64);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SavedPull {
+    id: String,
+    model: String,
+}
+
+fn queue_path() -> PathBuf {
+    get_data_dir().join("pulls.json")
+}
+
+#[derive(Default)]
+struct Pulls {
+    /// In submission order
+    pulls: Vec<PullInfo>,
+    cancels: HashMap<String, Arc<Notify>>,
+}
+
+impl Pulls {
+    fn get_mut(&mut self, id: &str) -> Option<&mut PullInfo> {
+        self.pulls.iter_mut().find(|p| p.id == id)
+    }
+
+    /// Persist unfinished pulls so they resume after a restart
+    fn save(&self) {
+        let pending: Vec<SavedPull> = self
+            .pulls
+            .iter()
+            .filter(|p| !p.state.is_finished())
+            .map(|p| SavedPull {
+                id: p.id.clone(),
+                model: p.model.clone(),
+            })
+            .collect();
+        let result = serde_json::to_string_pretty(&pending)
+            .map_err(|e| e.to_string())
+            .and_then(|json| std::fs::write(queue_path(), json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("[openworld] Failed to save pull queue: {}", e);
+        }
+    }
+}
+
+/// Model pulls, run one at a time in submission order
+pub struct PullManager {
+    pulls: Arc<Mutex<Pulls>>,
+    sender: UnboundedSender<String>,
+}
+
+impl PullManager {
+    /// Queue `model` for download and return the pull's id. Asking for a model that is
+    /// already queued or downloading returns the existing pull.
+    pub fn enqueue(&self, model: &str) -> Result<String, String> {
+        let model = model.trim();
+        if model.is_empty() {
+            return Err("Model name cannot be empty".to_string());
+        }
+        let mut pulls = self.pulls.lock().map_err(|e| e.to_string())?;
+        if let Some(existing) = pulls.pulls.iter().find(|p| p.model == model && !p.state.is_finished()) {
+            return Ok(existing.id.clone());
+        }
+        let id = Uuid::new_v4().to_string();
+        pulls.pulls.push(PullInfo::new(id.clone(), model.to_string()));
+        pulls.cancels.insert(id.clone(), Arc::new(Notify::new()));
+        pulls.save();
+        drop(pulls);
+        self.sender.send(id.clone()).map_err(|_| "Pull queue is closed".to_string())?;
+        Ok(id)
+    }
+
+    pub fn cancel(&self, id: &str) -> Result<(), String> {
+        let mut pulls = self.pulls.lock().map_err(|e| e.to_string())?;
+        let pull = pulls.get_mut(id).ok_or_else(|| format!("Pull not found: {}", id))?;
+        if pull.state.is_finished() {
+            return Ok(());
+        }
+        if pull.state == PullState::Queued {
+            // Never started; the worker skips it
+            pull.state = PullState::Cancelled;
+            pull.status = "Cancelled".to_string();
+            pulls.save();
+        }
+        if let Some(cancel) = pulls.cancels.get(id) {
+            cancel.notify_one();
+        }
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<PullInfo> {
+        self.pulls.lock().map(|p| p.pulls.clone()).unwrap_or_default()
+    }
+}
+
+/// Start the worker, re-queueing pulls that were unfinished when the app last quit
+pub fn start(app: AppHandle) -> PullManager {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let manager = PullManager {
+        pulls: Arc::new(Mutex::new(Pulls::default())),
+        sender,
+    };
+
+    let saved: Vec<SavedPull> = std::fs::read_to_string(queue_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    if let Ok(mut pulls) = manager.pulls.lock() {
+        for pull in saved {
+            eprintln!("[openworld] Resuming pull of {}", pull.model);
+            pulls.pulls.push(PullInfo::new(pull.id.clone(), pull.model));
+            pulls.cancels.insert(pull.id.clone(), Arc::new(Notify::new()));
+            let _ = manager.sender.send(pull.id);
+        }
+    }
+
+    tauri::async_runtime::spawn(worker(app, manager.pulls.clone(), receiver));
+    manager
+}
+
+async fn wait_for_engine() -> bool {
+    for _ in 0..ENGINE_WAIT_SECS / ENGINE_POLL_SECS {
+        if ollama::check_ollama_running().await {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_secs(ENGINE_POLL_SECS)).await;
+    }
+    false
+}
+
+/// Update a pull and send it to the frontend as `event`
+fn publish(app: &AppHandle, pulls: &Mutex<Pulls>, id: &str, event: &str, update: impl FnOnce(&mut PullInfo)) {
+    let Ok(mut pulls) = pulls.lock() else { return };
+    let Some(pull) = pulls.get_mut(id) else { return };
+    update(pull);
+    let _ = app.emit(event, &*pull);
+}
+
+async fn worker(app: AppHandle, pulls: Arc<Mutex<Pulls>>, mut receiver: UnboundedReceiver<String>) {
+    while let Some(id) = receiver.recv().await {
+        let (model, cancel) = {
+            let Ok(pulls) = pulls.lock() else { continue };
+            let Some(pull) = pulls.pulls.iter().find(|p| p.id == id) else { continue };
+            if pull.state != PullState::Queued {
+                if pull.state == PullState::Cancelled {
+                    let _ = app.emit("model-pull-finished", pull);
+                }
+                continue;
+            }
+            (pull.model.clone(), pulls.cancels.get(&id).cloned().unwrap_or_default())
+        };
+
+        publish(&app, &pulls, &id, "model-pull-progress", |p| {
+            p.state = PullState::Downloading;
+            p.status = "Waiting for AI engine...".to_string();
+        });
+        // A pull cancelled while waiting for the engine stops right away
+        let engine_ready = tokio::select! {
+            ready = wait_for_engine() => Some(ready),
+            _ = cancel.notified() => None,
+        };
+        let result = match engine_ready {
+            Some(true) => {
+                let mut tracker = PullTracker::default();
+                ollama::pull_model(&model, &cancel, |progress| {
+                    publish(&app, &pulls, &id, "model-pull-progress", |p| {
+                        tracker.update(progress, Instant::now(), p)
+                    });
+                })
+                .await
+            }
+            Some(false) => Err(OllamaError::EngineNotRunning),
+            None => Ok(false),
+        };
+
+        publish(&app, &pulls, &id, "model-pull-finished", |p| {
+            p.bytes_per_sec = None;
+            p.eta_secs = None;
+            match result {
+                Ok(true) => {
+                    p.state = PullState::Completed;
+                    p.status = "success".to_string();
+                    p.percent = Some(100.0);
+                }
+                Ok(false) => {
+                    p.state = PullState::Cancelled;
+                    p.status = "Cancelled".to_string();
+                }
+                Err(e) => {
+                    eprintln!("[openworld] Pull of {} failed: {}", p.model, e);
+                    p.state = PullState::Failed;
+                    p.status = "Failed".to_string();
+                    p.error = Some(e);
+                }
+            }
+        });
+        if let Ok(mut pulls) = pulls.lock() {
+            pulls.cancels.remove(&id);
+            pulls.save();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(digest: &str, completed: u64, total: u64) -> PullProgress {
+        PullProgress {
+            status: format!("pulling {}", digest),
+            digest: Some(digest.to_string()),
+            total: Some(total),
+            completed: Some(completed),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_tracker_aggregates_layers_speed_and_eta() {
+        let mut tracker = PullTracker::default();
+        let mut info = PullInfo::new("id".to_string(), "llama3:8b".to_string());
+        let start = Instant::now();
+
+        tracker.update(&layer("a", 0, 1000), start, &mut info);
+        tracker.update(&layer("b", 0, 3000), start, &mut info);
+        assert_eq!((info.completed, info.total, info.percent), (Some(0), Some(4000), Some(0.0)));
+        assert_eq!(info.eta_secs, None);
+
+        tracker.update(&layer("a", 1000, 1000), start + Duration::from_secs(1), &mut info);
+        tracker.update(&layer("b", 1000, 3000), start + Duration::from_secs(2), &mut info);
+        assert_eq!(info.percent, Some(50.0));
+        assert_eq!(info.bytes_per_sec, Some(1000.0));
+        assert_eq!(info.eta_secs, Some(2));
+
+        // Status-only lines keep the numbers
+        let status = PullProgress {
+            status: "verifying sha256 digest".to_string(),
+            digest: None,
+            total: None,
+            completed: None,
+            error: None,
+        };
+        tracker.update(&status, start + Duration::from_secs(3), &mut info);
+        assert_eq!(info.status, "verifying sha256 digest");
+        assert_eq!(info.percent, Some(50.0));
+    }
+
+    #[test]
+    fn test_speed_uses_recent_window() {
+        let mut tracker = PullTracker::default();
+        let mut info = PullInfo::new("id".to_string(), "m".to_string());
+        let start = Instant::now();
+        tracker.update(&layer("a", 0, 100_000), start, &mut info);
+        tracker.update(&layer("a", 50_000, 100_000), start + Duration::from_secs(1), &mut info);
+        // Stalled for a while: the early burst no longer counts
+        for s in 2..=10 {
+            tracker.update(&layer("a", 50_000, 100_000), start + Duration::from_secs(s), &mut info);
+        }
+        assert_eq!(info.bytes_per_sec, Some(0.0));
+        assert_eq!(info.eta_secs, None);
+    }
+}
@@ -0,0 +1,43 @@
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::config::{load_config, AppConfig};
+use crate::storage::StorageEngine;
+use crate::AppState;
+
+/// How often the background task re-applies the retention settings
+const RETENTION_INTERVAL_SECS: u64 = 60 * 60;
+
+/// Apply the global retention policy, then purge conversations that have been in the trash too long
+pub fn enforce(storage: &StorageEngine, config: &AppConfig) -> Result<(), String> {
+    if let Some(days) = config.conversation_retention_days {
+        let trashed = storage.trash_conversations_older_than(days)?;
+        if trashed > 0 {
+            eprintln!("[openworld] Retention: moved {} conversation(s) older than {} days to trash", trashed, days);
+        }
+    }
+
+    if config.trash_retention_days > 0 {
+        let purged = storage.purge_trash_older_than(config.trash_retention_days)?;
+        if purged > 0 {
+            eprintln!("[openworld] Retention: purged {} conversation(s) from trash", purged);
+        }
+    }
+    Ok(())
+}
+
+/// Background loop started from `run()`; re-reads the config each pass so changes apply without a restart
+pub async fn run(app: AppHandle) {
+    loop {
+        let config = load_config();
+        // Release the app state before purging; VACUUM can take a while on a large database
+        let managed_state = app.state::<Mutex<AppState>>();
+        let storage = managed_state.lock().ok().map(|app_state| app_state.storage.clone());
+        if let Some(storage) = storage {
+            if let Err(e) = enforce(&storage, &config) {
+                eprintln!("[openworld] Retention pass failed: {}", e);
+            }
+        }
+        tokio::time::sleep(tokio::time::Duration::from_secs(RETENTION_INTERVAL_SECS)).await;
+    }
+}
@@ -1,11 +1,13 @@
 use chrono::{DateTime, Utc};
-use rusqlite::{params, Connection};
+use rusqlite::{params, params_from_iter, Connection, Row};
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 use std::sync::Mutex;
 use uuid::Uuid;
 
-use crate::config::get_data_dir;
+use crate::chat::GenerationOptions;
 use crate::crypto::CryptoEngine;
+use crate::prompts::template_variables;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Conversation {
@@ -14,6 +16,319 @@ pub struct Conversation {
     pub created_at: String,
     pub updated_at: String,
     pub model: String,
+    #[serde(default)]
+    pub folder_id: Option<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub archived: bool,
+    /// Set when the conversation is in the trash
+    #[serde(default)]
+    pub deleted_at: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub memory_mode: MemoryMode,
+    /// Incognito conversations only live in memory and are never written to the database
+    #[serde(default)]
+    pub incognito: bool,
+    #[serde(default)]
+    pub persona_id: Option<String>,
+}
+
+/// Whether a conversation may read from and contribute to long-term memory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MemoryMode {
+    Off,
+    ReadOnly,
+    #[default]
+    ReadWrite,
+}
+
+impl MemoryMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemoryMode::Off => "off",
+            MemoryMode::ReadOnly => "read-only",
+            MemoryMode::ReadWrite => "read-write",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "off" => MemoryMode::Off,
+            "read-only" => MemoryMode::ReadOnly,
+            _ => MemoryMode::ReadWrite,
+        }
+    }
+
+    /// Memories are injected into the system prompt
+    pub fn reads(&self) -> bool {
+        *self != MemoryMode::Off
+    }
+
+    /// Facts extracted from the conversation are saved
+    pub fn writes(&self) -> bool {
+        *self == MemoryMode::ReadWrite
+    }
+}
+
+/// A saved assistant preset that a conversation can be created with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Persona {
+    pub id: String,
+    pub name: String,
+    pub system_prompt: String,
+    pub default_model: Option<String>,
+    pub options: GenerationOptions,
+    /// Memory mode given to conversations created with this persona
+    pub memory_mode: MemoryMode,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// The editable fields of a persona
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PersonaDraft {
+    pub name: String,
+    pub system_prompt: String,
+    pub default_model: Option<String>,
+    pub options: GenerationOptions,
+    pub memory_mode: MemoryMode,
+}
+
+const PERSONA_COLUMNS: &str = "id, name, system_prompt, default_model, options, memory_mode, created_at, updated_at";
+
+fn persona_from_row(row: &Row) -> rusqlite::Result<Persona> {
+    let options: String = row.get(4)?;
+    Ok(Persona {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        system_prompt: row.get(2)?,
+        default_model: row.get(3)?,
+        options: serde_json::from_str(&options).unwrap_or_default(),
+        memory_mode: MemoryMode::parse(&row.get::<_, String>(5)?),
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub id: String,
+    pub name: String,
+    pub content: String,
+    /// `{{variable}}` names found in `content`
+    pub variables: Vec<String>,
+    pub default_model: Option<String>,
+    pub options: GenerationOptions,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// The editable fields of a prompt template; also the shape used for import/export
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptTemplateDraft {
+    pub name: String,
+    pub content: String,
+    pub default_model: Option<String>,
+    pub options: GenerationOptions,
+}
+
+const PROMPT_TEMPLATE_COLUMNS: &str = "id, name, content, default_model, options, created_at, updated_at";
+
+fn prompt_template_from_row(row: &Row) -> rusqlite::Result<PromptTemplate> {
+    let content: String = row.get(2)?;
+    let options: String = row.get(4)?;
+    Ok(PromptTemplate {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        variables: template_variables(&content),
+        content,
+        default_model: row.get(3)?,
+        options: serde_json::from_str(&options).unwrap_or_default(),
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Folder {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: String,
+    pub name: String,
+}
+
+/// Filter for `list_conversations`. Every field is optional; `None` means "don't filter on this".
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConversationFilter {
+    pub folder_id: Option<String>,
+    pub tag_id: Option<String>,
+    pub pinned: Option<bool>,
+    pub archived: Option<bool>,
+    /// List trashed conversations instead of live ones
+    pub in_trash: bool,
+    pub search: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+const CONVERSATION_COLUMNS: &str =
+    "id, title, created_at, updated_at, model, folder_id, pinned, archived, deleted_at, memory_mode, persona_id";
+
+fn conversation_from_row(row: &Row) -> rusqlite::Result<Conversation> {
+    Ok(Conversation {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        created_at: row.get(2)?,
+        updated_at: row.get(3)?,
+        model: row.get(4)?,
+        folder_id: row.get(5)?,
+        pinned: row.get(6)?,
+        archived: row.get(7)?,
+        deleted_at: row.get(8)?,
+        tags: Vec::new(),
+        memory_mode: MemoryMode::parse(&row.get::<_, String>(9)?),
+        incognito: false,
+        persona_id: row.get(10)?,
+    })
+}
+
+fn load_tags(conn: &Connection, convos: &mut [Conversation]) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare("SELECT t.id, t.name FROM tags t JOIN conversation_tags ct ON ct.tag_id = t.id WHERE ct.conversation_id = ?1 ORDER BY t.name ASC")
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+    for convo in convos.iter_mut() {
+        convo.tags = stmt
+            .query_map(params![convo.id], |row| {
+                Ok(Tag {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                })
+            })
+            .map_err(|e| format!("Failed to query tags: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+    }
+    Ok(())
+}
+
+fn memory_content(conn: &Connection, id: &str) -> Result<String, String> {
+    conn.query_row("SELECT content FROM memories WHERE id = ?1", params![id], |row| row.get(0))
+        .map_err(|_| format!("Memory not found: {}", id))
+}
+
+/// Apply one memory operation on an open connection/transaction. Returns the affected memory ID.
+/// `source` is `None` for changes made by the user; an updated memory then keeps its provenance
+fn apply_memory_operation(conn: &Connection, op: &MemoryOperation, source: Option<&MemorySource>) -> Result<String, String> {
+    let now = Utc::now().to_rfc3339();
+    let message_ids = source
+        .map(|s| serde_json::to_string(&s.message_ids))
+        .transpose()
+        .map_err(|e| format!("Failed to serialize message ids: {}", e))?;
+
+    match op {
+        MemoryOperation::Add { fact } => {
+            let id = Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO memories (id, content, created_at, source_conversation_id, source_message_ids, extracted_by_model, extracted_at, category, importance, expires_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    id,
+                    fact.content,
+                    now,
+                    source.map(|s| &s.conversation_id),
+                    message_ids,
+                    source.map(|s| &s.model),
+                    source.map(|s| &s.extracted_at),
+                    fact.category.as_str(),
+                    fact.importance,
+                    fact.expires_at
+                ],
+            )
+            .map_err(|e| format!("Failed to add memory: {}", e))?;
+            Ok(id)
+        }
+        MemoryOperation::Update { id, fact } => {
+            let previous = memory_content(conn, id)?;
+            conn.execute(
+                "INSERT INTO memory_versions (id, memory_id, content, change, changed_at) VALUES (?1, ?2, ?3, 'update', ?4)",
+                params![Uuid::new_v4().to_string(), id, previous, now],
+            )
+            .map_err(|e| format!("Failed to record memory version: {}", e))?;
+            if let Some(source) = source {
+                conn.execute(
+                    "UPDATE memories SET source_conversation_id = ?1, source_message_ids = ?2, extracted_by_model = ?3, extracted_at = ?4
+                     WHERE id = ?5",
+                    params![source.conversation_id, message_ids, source.model, source.extracted_at, id],
+                )
+                .map_err(|e| format!("Failed to update memory: {}", e))?;
+            }
+            conn.execute(
+                "UPDATE memories SET content = ?1, category = ?2, importance = ?3, expires_at = ?4, embedding = NULL, embedding_model = NULL
+                 WHERE id = ?5",
+                params![fact.content, fact.category.as_str(), fact.importance, fact.expires_at, id],
+            )
+            .map_err(|e| format!("Failed to update memory: {}", e))?;
+            Ok(id.clone())
+        }
+        MemoryOperation::Delete { id } => {
+            let previous = memory_content(conn, id)?;
+            conn.execute(
+                "INSERT INTO memory_versions (id, memory_id, content, change, changed_at) VALUES (?1, ?2, ?3, 'delete', ?4)",
+                params![Uuid::new_v4().to_string(), id, previous, now],
+            )
+            .map_err(|e| format!("Failed to record memory version: {}", e))?;
+            conn.execute("DELETE FROM memories WHERE id = ?1", params![id])
+                .map_err(|e| format!("Failed to delete memory: {}", e))?;
+            Ok(id.clone())
+        }
+    }
+}
+
+fn encode_embedding(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode_embedding(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Add a column to an existing table if it isn't there yet (schema migration for older databases)
+fn ensure_column(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<(), String> {
+    let exists: bool = conn
+        .query_row(
+            &format!("SELECT EXISTS(SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1)", table),
+            params![column],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to inspect {} schema: {}", table, e))?;
+    if !exists {
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl))
+            .map_err(|e| format!("Failed to migrate {}.{}: {}", table, column, e))?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageEdit {
+    pub id: String,
+    pub message_id: String,
+    pub previous_content: String,
+    pub edited_at: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,14 +340,185 @@ pub struct Message {
     pub timestamp: String,
 }
 
+/// Where an auto-extracted memory came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemorySource {
+    pub conversation_id: String,
+    pub message_ids: Vec<String>,
+    pub model: String,
+    pub extracted_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryProvenance {
+    pub memory_id: String,
+    pub content: String,
+    pub created_at: String,
+    /// `None` for memories added by hand
+    pub source: Option<MemorySource>,
+    /// Decrypted source messages that still exist, in conversation order
+    pub excerpt: Vec<Message>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryCategory {
+    Identity,
+    Preferences,
+    Work,
+    Health,
+    Relationships,
+    #[default]
+    Other,
+}
+
+impl MemoryCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemoryCategory::Identity => "identity",
+            MemoryCategory::Preferences => "preferences",
+            MemoryCategory::Work => "work",
+            MemoryCategory::Health => "health",
+            MemoryCategory::Relationships => "relationships",
+            MemoryCategory::Other => "other",
+        }
+    }
+
+    /// Lenient parse; anything unrecognized becomes `Other`
+    pub fn parse(s: &str) -> Self {
+        match s.trim().to_lowercase().as_str() {
+            "identity" => MemoryCategory::Identity,
+            "preferences" | "preference" => MemoryCategory::Preferences,
+            "work" => MemoryCategory::Work,
+            "health" => MemoryCategory::Health,
+            "relationships" | "relationship" => MemoryCategory::Relationships,
+            _ => MemoryCategory::Other,
+        }
+    }
+}
+
+/// Importance is 1 (trivia) to 5 (essential)
+pub const DEFAULT_MEMORY_IMPORTANCE: u8 = 3;
+
+/// Maximum number of memories injected into the system prompt
+const MAX_CONTEXT_MEMORIES: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Memory {
+    pub id: String,
+    pub content: String,
+    pub created_at: String,
+    pub category: MemoryCategory,
+    pub importance: u8,
+    pub expires_at: Option<String>,
+}
+
+/// The content and attributes of a fact being added or updated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryFact {
+    pub content: String,
+    pub category: MemoryCategory,
+    pub importance: u8,
+    pub expires_at: Option<String>,
+}
+
+impl MemoryFact {
+    pub fn new(content: &str) -> Self {
+        Self {
+            content: content.to_string(),
+            category: MemoryCategory::Other,
+            importance: DEFAULT_MEMORY_IMPORTANCE,
+            expires_at: None,
+        }
+    }
+}
+
+/// `(memory id, content, cached embedding)`
+pub type MemoryEmbedding = (String, String, Option<Vec<f32>>);
+
+/// A change to long-term memory proposed by fact extraction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum MemoryOperation {
+    Add { fact: MemoryFact },
+    Update { id: String, fact: MemoryFact },
+    Delete { id: String },
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemoryChangeSummary {
+    pub added: usize,
+    pub updated: usize,
+    pub deleted: usize,
+}
+
+/// A previous text of a memory, recorded when it was updated or deleted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryVersion {
+    pub id: String,
+    pub memory_id: String,
+    pub content: String,
+    pub change: String, // "update" | "delete" | "merge"
+    pub changed_at: String,
+}
+
+/// An extraction operation waiting for the user to approve or reject it (review mode)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingMemory {
+    pub id: String,
+    pub operation: String, // "add" | "update" | "delete"
+    pub target_memory_id: Option<String>,
+    /// Proposed text for add/update, current text of the memory for delete
+    pub content: String,
+    pub category: MemoryCategory,
+    pub importance: u8,
+    pub expires_at: Option<String>,
+    pub created_at: String,
+    pub source: MemorySource,
+}
+
 pub struct StorageEngine {
     conn: Mutex<Connection>,
     crypto: CryptoEngine,
 }
 
+/// Overwrite, then delete the messages matching `condition` together with their edit
+/// history. The caller turns on `secure_delete` so the freed pages are zeroed as well.
+fn shred_messages(tx: &rusqlite::Transaction, condition: &str, args: &[&dyn rusqlite::ToSql]) -> Result<(), String> {
+    let messages = format!("SELECT id FROM messages WHERE {}", condition);
+    tx.execute(
+        &format!(
+            "UPDATE message_edits SET previous_content_encrypted = hex(randomblob(length(previous_content_encrypted)))
+             WHERE message_id IN ({})",
+            messages
+        ),
+        args,
+    )
+    .map_err(|e| format!("Failed to overwrite message edits: {}", e))?;
+    tx.execute(&format!("DELETE FROM message_edits WHERE message_id IN ({})", messages), args)
+        .map_err(|e| format!("Failed to delete message edits: {}", e))?;
+    tx.execute(
+        &format!(
+            "UPDATE messages SET content_encrypted = hex(randomblob(length(content_encrypted))) WHERE {}",
+            condition
+        ),
+        args,
+    )
+    .map_err(|e| format!("Failed to overwrite messages: {}", e))?;
+    tx.execute(&format!("DELETE FROM messages WHERE {}", condition), args)
+        .map_err(|e| format!("Failed to delete messages: {}", e))?;
+    Ok(())
+}
+
 impl StorageEngine {
-    pub fn new() -> Result<Self, String> {
-        let data_dir = get_data_dir();
+    /// Open (or create) the database and keyfile inside `data_dir`
+    pub fn open(data_dir: &Path) -> Result<Self, String> {
+        let master_secret = crate::crypto::get_or_create_master_secret(data_dir)?;
+        Self::open_with_secret(data_dir, &master_secret)
+    }
+
+    /// Open (or create) the database inside `data_dir`, encrypting with a key derived from `master_secret`
+    pub fn open_with_secret(data_dir: &Path, master_secret: &[u8]) -> Result<Self, String> {
         let db_path = data_dir.join("data.db");
 
         let conn = Connection::open(&db_path)
@@ -59,12 +545,97 @@ impl StorageEngine {
                 content TEXT NOT NULL,
                 created_at TEXT NOT NULL
             );
+            CREATE TABLE IF NOT EXISTS pending_memories (
+                id TEXT PRIMARY KEY,
+                content TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                source_conversation_id TEXT NOT NULL,
+                source_message_ids TEXT NOT NULL,
+                extracted_by_model TEXT NOT NULL,
+                extracted_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS memory_versions (
+                id TEXT PRIMARY KEY,
+                memory_id TEXT NOT NULL,
+                content TEXT NOT NULL,
+                change TEXT NOT NULL,
+                changed_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS message_edits (
+                id TEXT PRIMARY KEY,
+                message_id TEXT NOT NULL,
+                previous_content_encrypted TEXT NOT NULL,
+                edited_at TEXT NOT NULL,
+                FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE
+            );
+            CREATE TABLE IF NOT EXISTS personas (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                system_prompt TEXT NOT NULL,
+                default_model TEXT,
+                options TEXT NOT NULL,
+                memory_mode TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS prompt_templates (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                content TEXT NOT NULL,
+                default_model TEXT,
+                options TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS folders (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS tags (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE
+            );
+            CREATE TABLE IF NOT EXISTS conversation_tags (
+                conversation_id TEXT NOT NULL,
+                tag_id TEXT NOT NULL,
+                PRIMARY KEY (conversation_id, tag_id),
+                FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE,
+                FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+            );
+            CREATE TABLE IF NOT EXISTS imported_conversations (
+                source TEXT NOT NULL,
+                external_id TEXT NOT NULL,
+                conversation_id TEXT NOT NULL,
+                imported_at TEXT NOT NULL,
+                PRIMARY KEY (source, external_id)
+            );
             PRAGMA foreign_keys = ON;",
         )
         .map_err(|e| format!("Failed to create tables: {}", e))?;
 
-        let master_secret = crate::crypto::get_or_create_master_secret(&data_dir)?;
-        let crypto = CryptoEngine::new(&master_secret)?;
+        // Organization columns added after the initial schema
+        ensure_column(&conn, "conversations", "folder_id", "TEXT")?;
+        ensure_column(&conn, "conversations", "pinned", "INTEGER NOT NULL DEFAULT 0")?;
+        ensure_column(&conn, "conversations", "archived", "INTEGER NOT NULL DEFAULT 0")?;
+        ensure_column(&conn, "conversations", "deleted_at", "TEXT")?;
+        ensure_column(&conn, "conversations", "memory_mode", "TEXT NOT NULL DEFAULT 'read-write'")?;
+        ensure_column(&conn, "conversations", "persona_id", "TEXT")?;
+        ensure_column(&conn, "memories", "source_conversation_id", "TEXT")?;
+        ensure_column(&conn, "memories", "source_message_ids", "TEXT")?;
+        ensure_column(&conn, "memories", "extracted_by_model", "TEXT")?;
+        ensure_column(&conn, "memories", "extracted_at", "TEXT")?;
+        ensure_column(&conn, "pending_memories", "operation", "TEXT NOT NULL DEFAULT 'add'")?;
+        ensure_column(&conn, "pending_memories", "target_memory_id", "TEXT")?;
+        for table in ["memories", "pending_memories"] {
+            ensure_column(&conn, table, "category", "TEXT NOT NULL DEFAULT 'other'")?;
+            ensure_column(&conn, table, "importance", "INTEGER NOT NULL DEFAULT 3")?;
+            ensure_column(&conn, table, "expires_at", "TEXT")?;
+        }
+        ensure_column(&conn, "memories", "embedding", "BLOB")?;
+        ensure_column(&conn, "memories", "embedding_model", "TEXT")?;
+
+        let crypto = CryptoEngine::new(master_secret)?;
 
         Ok(Self {
             conn: Mutex::new(conn),
@@ -72,15 +643,21 @@ impl StorageEngine {
         })
     }
 
-    pub fn create_conversation(&self, title: &str, model: &str) -> Result<Conversation, String> {
+    pub fn create_conversation(
+        &self,
+        title: &str,
+        model: &str,
+        memory_mode: MemoryMode,
+        persona_id: Option<&str>,
+    ) -> Result<Conversation, String> {
         let id = Uuid::new_v4().to_string();
         let now: DateTime<Utc> = Utc::now();
         let now_str = now.to_rfc3339();
 
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
         conn.execute(
-            "INSERT INTO conversations (id, title, created_at, updated_at, model) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![id, title, now_str, now_str, model],
+            "INSERT INTO conversations (id, title, created_at, updated_at, model, memory_mode, persona_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![id, title, now_str, now_str, model, memory_mode.as_str(), persona_id],
         )
         .map_err(|e| format!("Failed to create conversation: {}", e))?;
 
@@ -90,32 +667,85 @@ impl StorageEngine {
             created_at: now_str.clone(),
             updated_at: now_str,
             model: model.to_string(),
+            folder_id: None,
+            pinned: false,
+            archived: false,
+            deleted_at: None,
+            tags: Vec::new(),
+            memory_mode,
+            incognito: false,
+            persona_id: persona_id.map(str::to_string),
         })
     }
 
-    pub fn list_conversations(&self) -> Result<Vec<Conversation>, String> {
+    pub fn list_conversations(&self, filter: &ConversationFilter) -> Result<Vec<Conversation>, String> {
+        let mut sql = format!(
+            "SELECT {} FROM conversations WHERE {}",
+            CONVERSATION_COLUMNS,
+            if filter.in_trash { "deleted_at IS NOT NULL" } else { "deleted_at IS NULL" }
+        );
+        let mut args: Vec<rusqlite::types::Value> = Vec::new();
+
+        if let Some(folder_id) = &filter.folder_id {
+            sql.push_str(" AND folder_id = ?");
+            args.push(folder_id.clone().into());
+        }
+        if let Some(tag_id) = &filter.tag_id {
+            sql.push_str(" AND id IN (SELECT conversation_id FROM conversation_tags WHERE tag_id = ?)");
+            args.push(tag_id.clone().into());
+        }
+        if let Some(pinned) = filter.pinned {
+            sql.push_str(" AND pinned = ?");
+            args.push(pinned.into());
+        }
+        if let Some(archived) = filter.archived {
+            sql.push_str(" AND archived = ?");
+            args.push(archived.into());
+        }
+        if let Some(search) = filter.search.as_deref().filter(|s| !s.trim().is_empty()) {
+            let escaped = search.trim().replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            sql.push_str(" AND title LIKE ? ESCAPE '\\'");
+            args.push(format!("%{}%", escaped).into());
+        }
+
+        sql.push_str(" ORDER BY pinned DESC, updated_at DESC");
+        if filter.limit.is_some() || filter.offset.is_some() {
+            sql.push_str(" LIMIT ? OFFSET ?");
+            args.push(filter.limit.map_or(-1, i64::from).into());
+            args.push(i64::from(filter.offset.unwrap_or(0)).into());
+        }
+
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
         let mut stmt = conn
-            .prepare("SELECT id, title, created_at, updated_at, model FROM conversations ORDER BY updated_at DESC")
+            .prepare(&sql)
             .map_err(|e| format!("Failed to prepare query: {}", e))?;
 
-        let convos = stmt
-            .query_map([], |row| {
-                Ok(Conversation {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    created_at: row.get(2)?,
-                    updated_at: row.get(3)?,
-                    model: row.get(4)?,
-                })
-            })
+        let mut convos: Vec<Conversation> = stmt
+            .query_map(params_from_iter(args), conversation_from_row)
             .map_err(|e| format!("Failed to query conversations: {}", e))?
             .filter_map(|r| r.ok())
             .collect();
 
+        load_tags(&conn, &mut convos)?;
         Ok(convos)
     }
 
+    pub fn get_conversation(&self, id: &str) -> Result<Option<Conversation>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(&format!("SELECT {} FROM conversations WHERE id = ?1", CONVERSATION_COLUMNS))
+            .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+        let mut convos: Vec<Conversation> = stmt
+            .query_map(params![id], conversation_from_row)
+            .map_err(|e| format!("Failed to query conversation: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        load_tags(&conn, &mut convos)?;
+        Ok(convos.pop())
+    }
+
     pub fn update_conversation_title(&self, id: &str, title: &str) -> Result<(), String> {
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
         let now = Utc::now().to_rfc3339();
@@ -127,15 +757,140 @@ impl StorageEngine {
         Ok(())
     }
 
+    pub fn set_conversation_memory_mode(&self, id: &str, mode: MemoryMode) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE conversations SET memory_mode = ?1 WHERE id = ?2",
+            params![mode.as_str(), id],
+        )
+        .map_err(|e| format!("Failed to update memory mode: {}", e))?;
+        Ok(())
+    }
+
+    pub fn set_conversation_pinned(&self, id: &str, pinned: bool) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE conversations SET pinned = ?1 WHERE id = ?2",
+            params![pinned, id],
+        )
+        .map_err(|e| format!("Failed to update pinned flag: {}", e))?;
+        Ok(())
+    }
+
+    pub fn set_conversation_archived(&self, id: &str, archived: bool) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE conversations SET archived = ?1 WHERE id = ?2",
+            params![archived, id],
+        )
+        .map_err(|e| format!("Failed to update archived flag: {}", e))?;
+        Ok(())
+    }
+
+    pub fn move_conversation_to_folder(&self, id: &str, folder_id: Option<&str>) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE conversations SET folder_id = ?1 WHERE id = ?2",
+            params![folder_id, id],
+        )
+        .map_err(|e| format!("Failed to move conversation: {}", e))?;
+        Ok(())
+    }
+
+    /// Move a conversation to the trash. It can be restored until it is purged.
     pub fn delete_conversation(&self, id: &str) -> Result<(), String> {
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
-        conn.execute("DELETE FROM messages WHERE conversation_id = ?1", params![id])
-            .map_err(|e| format!("Failed to delete messages: {}", e))?;
-        conn.execute("DELETE FROM conversations WHERE id = ?1", params![id])
-            .map_err(|e| format!("Failed to delete conversation: {}", e))?;
+        conn.execute(
+            "UPDATE conversations SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+            params![Utc::now().to_rfc3339(), id],
+        )
+        .map_err(|e| format!("Failed to move conversation to trash: {}", e))?;
+        Ok(())
+    }
+
+    pub fn restore_conversation(&self, id: &str) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE conversations SET deleted_at = NULL WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| format!("Failed to restore conversation: {}", e))?;
+        Ok(())
+    }
+
+    /// Permanently delete a single conversation (trashed or not)
+    pub fn purge_conversation(&self, id: &str) -> Result<(), String> {
+        self.purge_where("id = ?1", &[id.to_string().into()])?;
         Ok(())
     }
 
+    /// Permanently delete everything in the trash
+    pub fn empty_trash(&self) -> Result<usize, String> {
+        self.purge_where("deleted_at IS NOT NULL", &[])
+    }
+
+    /// Permanently delete conversations that have been in the trash longer than `days`
+    pub fn purge_trash_older_than(&self, days: u32) -> Result<usize, String> {
+        let cutoff = (Utc::now() - chrono::Duration::days(days as i64)).to_rfc3339();
+        self.purge_where("deleted_at IS NOT NULL AND deleted_at < ?1", &[cutoff.into()])
+    }
+
+    /// Move unpinned conversations not updated in the last `days` to the trash
+    pub fn trash_conversations_older_than(&self, days: u32) -> Result<usize, String> {
+        let now = Utc::now();
+        let cutoff = (now - chrono::Duration::days(days as i64)).to_rfc3339();
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE conversations SET deleted_at = ?1 WHERE deleted_at IS NULL AND pinned = 0 AND updated_at < ?2",
+            params![now.to_rfc3339(), cutoff],
+        )
+        .map_err(|e| format!("Failed to apply retention policy: {}", e))
+    }
+
+    /// Securely remove the conversations matching `condition`: overwrite their
+    /// content in place, delete every dependent row, then VACUUM so nothing
+    /// is left behind in free pages. Import records (ids only, no content) are kept
+    /// so a purged chat isn't brought back by the next import. Returns the number of
+    /// conversations removed.
+    fn purge_where(&self, condition: &str, args: &[rusqlite::types::Value]) -> Result<usize, String> {
+        let mut conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute_batch("PRAGMA secure_delete = ON;")
+            .map_err(|e| format!("Failed to enable secure delete: {}", e))?;
+
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+        let ids: Vec<String> = {
+            let mut stmt = tx
+                .prepare(&format!("SELECT id FROM conversations WHERE {}", condition))
+                .map_err(|e| format!("Failed to prepare query: {}", e))?;
+            let rows = stmt
+                .query_map(params_from_iter(args), |row| row.get(0))
+                .map_err(|e| format!("Failed to query conversations: {}", e))?;
+            rows.filter_map(|r| r.ok()).collect()
+        };
+
+        for id in &ids {
+            shred_messages(&tx, "conversation_id = ?1", params![id])?;
+            tx.execute("DELETE FROM conversation_tags WHERE conversation_id = ?1", params![id])
+                .map_err(|e| format!("Failed to delete conversation tags: {}", e))?;
+            tx.execute(
+                "UPDATE conversations SET title = hex(randomblob(length(title))) WHERE id = ?1",
+                params![id],
+            )
+            .map_err(|e| format!("Failed to overwrite conversation: {}", e))?;
+            tx.execute("DELETE FROM conversations WHERE id = ?1", params![id])
+                .map_err(|e| format!("Failed to delete conversation: {}", e))?;
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to commit purge: {}", e))?;
+
+        if !ids.is_empty() {
+            conn.execute_batch("VACUUM;")
+                .map_err(|e| format!("Failed to vacuum database: {}", e))?;
+        }
+        Ok(ids.len())
+    }
 
     pub fn add_message(
         &self,
@@ -171,25 +926,221 @@ impl StorageEngine {
         })
     }
 
-    pub fn get_messages(&self, conversation_id: &str) -> Result<Vec<Message>, String> {
+    /// Replace the content of a user message, keeping the old text in `message_edits`,
+    /// and drop every message that came after it so the reply can be regenerated.
+    pub fn edit_message(&self, message_id: &str, new_content: &str) -> Result<Message, String> {
+        let mut conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute_batch("PRAGMA secure_delete = ON;")
+            .map_err(|e| format!("Failed to enable secure delete: {}", e))?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        let (conversation_id, role, old_encrypted, timestamp, seq): (String, String, String, String, i64) = tx
+            .query_row(
+                "SELECT conversation_id, role, content_encrypted, timestamp, rowid FROM messages WHERE id = ?1",
+                params![message_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+            )
+            .map_err(|e| format!("Message not found: {}", e))?;
+
+        if role != "user" {
+            return Err("Only user messages can be edited".to_string());
+        }
+
+        let now = Utc::now().to_rfc3339();
+        tx.execute(
+            "INSERT INTO message_edits (id, message_id, previous_content_encrypted, edited_at) VALUES (?1, ?2, ?3, ?4)",
+            params![Uuid::new_v4().to_string(), message_id, old_encrypted, now],
+        )
+        .map_err(|e| format!("Failed to record edit: {}", e))?;
+
+        let encrypted = self.crypto.encrypt(new_content)?;
+        tx.execute(
+            "UPDATE messages SET content_encrypted = ?1 WHERE id = ?2",
+            params![encrypted, message_id],
+        )
+        .map_err(|e| format!("Failed to edit message: {}", e))?;
+
+        // Everything after the edited message goes, in the order the messages were added
+        shred_messages(&tx, "conversation_id = ?1 AND rowid > ?2", params![conversation_id, seq])?;
+
+        tx.execute(
+            "UPDATE conversations SET updated_at = ?1 WHERE id = ?2",
+            params![now, conversation_id],
+        )
+        .map_err(|e| format!("Failed to update conversation timestamp: {}", e))?;
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit edit: {}", e))?;
+
+        Ok(Message {
+            id: message_id.to_string(),
+            conversation_id,
+            role,
+            content: new_content.to_string(),
+            timestamp,
+        })
+    }
+
+    /// Previous versions of a message, oldest first
+    pub fn get_message_edits(&self, message_id: &str) -> Result<Vec<MessageEdit>, String> {
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
         let mut stmt = conn
-            .prepare("SELECT id, conversation_id, role, content_encrypted, timestamp FROM messages WHERE conversation_id = ?1 ORDER BY timestamp ASC")
+            .prepare("SELECT id, message_id, previous_content_encrypted, edited_at FROM message_edits WHERE message_id = ?1 ORDER BY edited_at ASC")
             .map_err(|e| format!("Failed to prepare query: {}", e))?;
 
-        let messages: Vec<Message> = stmt
-            .query_map(params![conversation_id], |row| {
+        let edits = stmt
+            .query_map(params![message_id], |row| {
                 Ok((
                     row.get::<_, String>(0)?,
                     row.get::<_, String>(1)?,
                     row.get::<_, String>(2)?,
                     row.get::<_, String>(3)?,
-                    row.get::<_, String>(4)?,
                 ))
             })
-            .map_err(|e| format!("Failed to query messages: {}", e))?
+            .map_err(|e| format!("Failed to query message edits: {}", e))?
             .filter_map(|r| r.ok())
-            .map(|(id, conv_id, role, encrypted, timestamp)| {
+            .map(|(id, message_id, encrypted, edited_at)| MessageEdit {
+                id,
+                message_id,
+                previous_content: self.crypto.decrypt(&encrypted).unwrap_or_else(|_| "[Decryption failed]".to_string()),
+                edited_at,
+            })
+            .collect();
+
+        Ok(edits)
+    }
+
+    /// Insert a conversation from another client with its original timestamps.
+    /// Returns `Ok(false)` without writing anything if `(source, external_id)` was imported before.
+    pub fn import_conversation(
+        &self,
+        source: &str,
+        external_id: &str,
+        conversation: &Conversation,
+        messages: &[(String, String, String)], // (role, plaintext content, timestamp)
+    ) -> Result<bool, String> {
+        let mut conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        let already_imported: bool = tx
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM imported_conversations WHERE source = ?1 AND external_id = ?2)
+                    OR EXISTS(SELECT 1 FROM conversations WHERE id = ?3)",
+                params![source, external_id, conversation.id],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to check import history: {}", e))?;
+        if already_imported {
+            return Ok(false);
+        }
+
+        tx.execute(
+            "INSERT INTO conversations (id, title, created_at, updated_at, model) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                conversation.id,
+                conversation.title,
+                conversation.created_at,
+                conversation.updated_at,
+                conversation.model
+            ],
+        )
+        .map_err(|e| format!("Failed to import conversation: {}", e))?;
+
+        for (role, content, timestamp) in messages {
+            let encrypted = self.crypto.encrypt(content)?;
+            tx.execute(
+                "INSERT INTO messages (id, conversation_id, role, content_encrypted, timestamp) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![Uuid::new_v4().to_string(), conversation.id, role, encrypted, timestamp],
+            )
+            .map_err(|e| format!("Failed to import message: {}", e))?;
+        }
+
+        tx.execute(
+            "INSERT INTO imported_conversations (source, external_id, conversation_id, imported_at) VALUES (?1, ?2, ?3, ?4)",
+            params![source, external_id, conversation.id, Utc::now().to_rfc3339()],
+        )
+        .map_err(|e| format!("Failed to record import: {}", e))?;
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit import: {}", e))?;
+        Ok(true)
+    }
+
+    /// IDs of the last `n` messages in a conversation, oldest first
+    pub fn recent_message_ids(&self, conversation_id: &str, n: usize) -> Result<Vec<String>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare("SELECT id FROM messages WHERE conversation_id = ?1 ORDER BY rowid DESC LIMIT ?2")
+            .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+        let mut ids: Vec<String> = stmt
+            .query_map(params![conversation_id, n as i64], |row| row.get(0))
+            .map_err(|e| format!("Failed to query messages: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+        ids.reverse();
+        Ok(ids)
+    }
+
+    /// Decrypt the given messages, skipping IDs that no longer exist
+    pub fn get_messages_by_ids(&self, ids: &[String]) -> Result<Vec<Message>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare("SELECT id, conversation_id, role, content_encrypted, timestamp FROM messages WHERE id = ?1")
+            .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+        let mut messages = Vec::new();
+        for id in ids {
+            let row = stmt
+                .query_map(params![id], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                    ))
+                })
+                .map_err(|e| format!("Failed to query message: {}", e))?
+                .filter_map(|r| r.ok())
+                .next();
+            if let Some((id, conv_id, role, encrypted, timestamp)) = row {
+                messages.push(Message {
+                    id,
+                    conversation_id: conv_id,
+                    role,
+                    content: self.crypto.decrypt(&encrypted).unwrap_or_else(|_| "[Decryption failed]".to_string()),
+                    timestamp,
+                });
+            }
+        }
+        messages.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        Ok(messages)
+    }
+
+    pub fn get_messages(&self, conversation_id: &str) -> Result<Vec<Message>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare("SELECT id, conversation_id, role, content_encrypted, timestamp FROM messages WHERE conversation_id = ?1 ORDER BY rowid ASC")
+            .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+        let messages: Vec<Message> = stmt
+            .query_map(params![conversation_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            })
+            .map_err(|e| format!("Failed to query messages: {}", e))?
+            .filter_map(|r| r.ok())
+            .map(|(id, conv_id, role, encrypted, timestamp)| {
                 let content = self.crypto.decrypt(&encrypted).unwrap_or_else(|_| "[Decryption failed]".to_string());
                 Message {
                     id,
@@ -204,33 +1155,580 @@ impl StorageEngine {
         Ok(messages)
     }
 
+    // ── Personas ─────────────────────────────────────────────────────────
+
+    pub fn create_persona(&self, draft: &PersonaDraft) -> Result<Persona, String> {
+        let name = draft.name.trim();
+        if name.is_empty() {
+            return Err("Persona name cannot be empty".to_string());
+        }
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+        let options = serde_json::to_string(&draft.options)
+            .map_err(|e| format!("Failed to serialize options: {}", e))?;
+
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO personas (id, name, system_prompt, default_model, options, memory_mode, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![id, name, draft.system_prompt, draft.default_model, options, draft.memory_mode.as_str(), now, now],
+        )
+        .map_err(|e| format!("Failed to create persona: {}", e))?;
+
+        Ok(Persona {
+            id,
+            name: name.to_string(),
+            system_prompt: draft.system_prompt.clone(),
+            default_model: draft.default_model.clone(),
+            options: draft.options.clone(),
+            memory_mode: draft.memory_mode,
+            created_at: now.clone(),
+            updated_at: now,
+        })
+    }
+
+    pub fn update_persona(&self, id: &str, draft: &PersonaDraft) -> Result<(), String> {
+        let name = draft.name.trim();
+        if name.is_empty() {
+            return Err("Persona name cannot be empty".to_string());
+        }
+        let options = serde_json::to_string(&draft.options)
+            .map_err(|e| format!("Failed to serialize options: {}", e))?;
+
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let updated = conn
+            .execute(
+                "UPDATE personas SET name = ?1, system_prompt = ?2, default_model = ?3, options = ?4, memory_mode = ?5, updated_at = ?6
+                 WHERE id = ?7",
+                params![
+                    name,
+                    draft.system_prompt,
+                    draft.default_model,
+                    options,
+                    draft.memory_mode.as_str(),
+                    Utc::now().to_rfc3339(),
+                    id
+                ],
+            )
+            .map_err(|e| format!("Failed to update persona: {}", e))?;
+        if updated == 0 {
+            return Err(format!("Persona not found: {}", id));
+        }
+        Ok(())
+    }
+
+    pub fn get_persona(&self, id: &str) -> Result<Option<Persona>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(&format!("SELECT {} FROM personas WHERE id = ?1", PERSONA_COLUMNS))
+            .map_err(|e| format!("Failed to prepare: {}", e))?;
+        let mut rows = stmt
+            .query_map(params![id], persona_from_row)
+            .map_err(|e| format!("Failed to query persona: {}", e))?;
+        Ok(rows.next().and_then(|r| r.ok()))
+    }
+
+    pub fn list_personas(&self) -> Result<Vec<Persona>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(&format!("SELECT {} FROM personas ORDER BY name COLLATE NOCASE ASC", PERSONA_COLUMNS))
+            .map_err(|e| format!("Failed to prepare: {}", e))?;
+
+        let personas = stmt
+            .query_map([], persona_from_row)
+            .map_err(|e| format!("Failed to query personas: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(personas)
+    }
+
+    /// Delete a persona; its conversations fall back to the global system prompt
+    pub fn delete_persona(&self, id: &str) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute("UPDATE conversations SET persona_id = NULL WHERE persona_id = ?1", params![id])
+            .map_err(|e| format!("Failed to detach persona: {}", e))?;
+        conn.execute("DELETE FROM personas WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to delete persona: {}", e))?;
+        Ok(())
+    }
+
+    // ── Prompt Templates ─────────────────────────────────────────────────
+
+    pub fn create_prompt_template(&self, draft: &PromptTemplateDraft) -> Result<PromptTemplate, String> {
+        let name = draft.name.trim();
+        if name.is_empty() {
+            return Err("Template name cannot be empty".to_string());
+        }
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+        let options = serde_json::to_string(&draft.options)
+            .map_err(|e| format!("Failed to serialize options: {}", e))?;
+
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO prompt_templates (id, name, content, default_model, options, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![id, name, draft.content, draft.default_model, options, now, now],
+        )
+        .map_err(|e| format!("Failed to create prompt template: {}", e))?;
+
+        Ok(PromptTemplate {
+            id,
+            name: name.to_string(),
+            content: draft.content.clone(),
+            variables: template_variables(&draft.content),
+            default_model: draft.default_model.clone(),
+            options: draft.options.clone(),
+            created_at: now.clone(),
+            updated_at: now,
+        })
+    }
+
+    pub fn update_prompt_template(&self, id: &str, draft: &PromptTemplateDraft) -> Result<(), String> {
+        let name = draft.name.trim();
+        if name.is_empty() {
+            return Err("Template name cannot be empty".to_string());
+        }
+        let options = serde_json::to_string(&draft.options)
+            .map_err(|e| format!("Failed to serialize options: {}", e))?;
+
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let updated = conn
+            .execute(
+                "UPDATE prompt_templates SET name = ?1, content = ?2, default_model = ?3, options = ?4, updated_at = ?5
+                 WHERE id = ?6",
+                params![name, draft.content, draft.default_model, options, Utc::now().to_rfc3339(), id],
+            )
+            .map_err(|e| format!("Failed to update prompt template: {}", e))?;
+        if updated == 0 {
+            return Err(format!("Prompt template not found: {}", id));
+        }
+        Ok(())
+    }
+
+    pub fn get_prompt_template(&self, id: &str) -> Result<Option<PromptTemplate>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(&format!("SELECT {} FROM prompt_templates WHERE id = ?1", PROMPT_TEMPLATE_COLUMNS))
+            .map_err(|e| format!("Failed to prepare: {}", e))?;
+        let mut rows = stmt
+            .query_map(params![id], prompt_template_from_row)
+            .map_err(|e| format!("Failed to query prompt template: {}", e))?;
+        Ok(rows.next().and_then(|r| r.ok()))
+    }
+
+    pub fn list_prompt_templates(&self) -> Result<Vec<PromptTemplate>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM prompt_templates ORDER BY name COLLATE NOCASE ASC",
+                PROMPT_TEMPLATE_COLUMNS
+            ))
+            .map_err(|e| format!("Failed to prepare: {}", e))?;
+
+        let templates = stmt
+            .query_map([], prompt_template_from_row)
+            .map_err(|e| format!("Failed to query prompt templates: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(templates)
+    }
+
+    pub fn delete_prompt_template(&self, id: &str) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute("DELETE FROM prompt_templates WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to delete prompt template: {}", e))?;
+        Ok(())
+    }
+
+    // ── Folders & Tags ───────────────────────────────────────────────────
+
+    pub fn create_folder(&self, name: &str) -> Result<Folder, String> {
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO folders (id, name, created_at) VALUES (?1, ?2, ?3)",
+            params![id, name, now],
+        )
+        .map_err(|e| format!("Failed to create folder: {}", e))?;
+        Ok(Folder {
+            id,
+            name: name.to_string(),
+            created_at: now,
+        })
+    }
+
+    pub fn list_folders(&self) -> Result<Vec<Folder>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare("SELECT id, name, created_at FROM folders ORDER BY name COLLATE NOCASE ASC")
+            .map_err(|e| format!("Failed to prepare: {}", e))?;
+
+        let folders = stmt
+            .query_map([], |row| {
+                Ok(Folder {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    created_at: row.get(2)?,
+                })
+            })
+            .map_err(|e| format!("Failed to query folders: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(folders)
+    }
+
+    pub fn rename_folder(&self, id: &str, name: &str) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute("UPDATE folders SET name = ?1 WHERE id = ?2", params![name, id])
+            .map_err(|e| format!("Failed to rename folder: {}", e))?;
+        Ok(())
+    }
+
+    /// Delete a folder; its conversations are kept and become unfiled
+    pub fn delete_folder(&self, id: &str) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute("UPDATE conversations SET folder_id = NULL WHERE folder_id = ?1", params![id])
+            .map_err(|e| format!("Failed to unfile conversations: {}", e))?;
+        conn.execute("DELETE FROM folders WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to delete folder: {}", e))?;
+        Ok(())
+    }
+
+    /// Create a tag, or return the existing one with the same name
+    pub fn create_tag(&self, name: &str) -> Result<Tag, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Tag name cannot be empty".to_string());
+        }
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT OR IGNORE INTO tags (id, name) VALUES (?1, ?2)",
+            params![Uuid::new_v4().to_string(), name],
+        )
+        .map_err(|e| format!("Failed to create tag: {}", e))?;
+        conn.query_row("SELECT id, name FROM tags WHERE name = ?1", params![name], |row| {
+            Ok(Tag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+            })
+        })
+        .map_err(|e| format!("Failed to load tag: {}", e))
+    }
+
+    pub fn list_tags(&self) -> Result<Vec<Tag>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare("SELECT id, name FROM tags ORDER BY name COLLATE NOCASE ASC")
+            .map_err(|e| format!("Failed to prepare: {}", e))?;
+
+        let tags = stmt
+            .query_map([], |row| {
+                Ok(Tag {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                })
+            })
+            .map_err(|e| format!("Failed to query tags: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(tags)
+    }
+
+    pub fn delete_tag(&self, id: &str) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute("DELETE FROM conversation_tags WHERE tag_id = ?1", params![id])
+            .map_err(|e| format!("Failed to untag conversations: {}", e))?;
+        conn.execute("DELETE FROM tags WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to delete tag: {}", e))?;
+        Ok(())
+    }
+
+    pub fn tag_conversation(&self, conversation_id: &str, tag_id: &str) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT OR IGNORE INTO conversation_tags (conversation_id, tag_id) VALUES (?1, ?2)",
+            params![conversation_id, tag_id],
+        )
+        .map_err(|e| format!("Failed to tag conversation: {}", e))?;
+        Ok(())
+    }
+
+    pub fn untag_conversation(&self, conversation_id: &str, tag_id: &str) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "DELETE FROM conversation_tags WHERE conversation_id = ?1 AND tag_id = ?2",
+            params![conversation_id, tag_id],
+        )
+        .map_err(|e| format!("Failed to untag conversation: {}", e))?;
+        Ok(())
+    }
+
     // ── Memory System ────────────────────────────────────────────────────
 
-    pub fn add_memory(&self, content: &str) -> Result<String, String> {
+    pub fn add_memory(&self, fact: &MemoryFact) -> Result<String, String> {
         let id = Uuid::new_v4().to_string();
         let now: DateTime<Utc> = Utc::now();
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
         conn.execute(
-            "INSERT INTO memories (id, content, created_at) VALUES (?1, ?2, ?3)",
-            params![id, content, now.to_rfc3339()],
+            "INSERT INTO memories (id, content, created_at, category, importance, expires_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id, fact.content, now.to_rfc3339(), fact.category.as_str(), fact.importance, fact.expires_at],
         )
         .map_err(|e| format!("Failed to add memory: {}", e))?;
         Ok(id)
     }
 
-    pub fn list_memories(&self) -> Result<Vec<(String, String, String)>, String> {
+    /// Apply the add / update / delete operations produced by fact extraction in
+    /// a single transaction. Updated and deleted facts keep their previous text in
+    /// `memory_versions`.
+    pub fn apply_memory_operations(
+        &self,
+        ops: &[MemoryOperation],
+        source: &MemorySource,
+    ) -> Result<MemoryChangeSummary, String> {
+        let mut conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        let mut summary = MemoryChangeSummary::default();
+        for op in ops {
+            apply_memory_operation(&tx, op, Some(source))?;
+            match op {
+                MemoryOperation::Add { .. } => summary.added += 1,
+                MemoryOperation::Update { .. } => summary.updated += 1,
+                MemoryOperation::Delete { .. } => summary.deleted += 1,
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit memory changes: {}", e))?;
+        Ok(summary)
+    }
+
+    /// Apply one add or update made by the user (e.g. a manually added fact that turned
+    /// out to restate a stored one). Returns the affected memory ID.
+    pub fn save_memory_operation(&self, op: &MemoryOperation) -> Result<String, String> {
+        let mut conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+        let memory_id = apply_memory_operation(&tx, op, None)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit memory change: {}", e))?;
+        Ok(memory_id)
+    }
+
+    /// Previous versions of a memory, oldest first
+    pub fn get_memory_history(&self, memory_id: &str) -> Result<Vec<MemoryVersion>, String> {
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
         let mut stmt = conn
-            .prepare("SELECT id, content, created_at FROM memories ORDER BY created_at ASC")
+            .prepare("SELECT id, memory_id, content, change, changed_at FROM memory_versions WHERE memory_id = ?1 ORDER BY changed_at ASC")
+            .map_err(|e| format!("Failed to prepare: {}", e))?;
+
+        let versions = stmt
+            .query_map(params![memory_id], |row| {
+                Ok(MemoryVersion {
+                    id: row.get(0)?,
+                    memory_id: row.get(1)?,
+                    content: row.get(2)?,
+                    change: row.get(3)?,
+                    changed_at: row.get(4)?,
+                })
+            })
+            .map_err(|e| format!("Failed to query memory history: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(versions)
+    }
+
+    // ── Memory Review Queue ──────────────────────────────────────────────
+
+    /// Queue an extraction operation for review instead of applying it
+    pub fn add_pending_operation(&self, op: &MemoryOperation, source: &MemorySource) -> Result<String, String> {
+        let id = Uuid::new_v4().to_string();
+        let message_ids = serde_json::to_string(&source.message_ids)
+            .map_err(|e| format!("Failed to serialize message ids: {}", e))?;
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+
+        // Deletes show the text that would be removed
+        let (kind, target, fact) = match op {
+            MemoryOperation::Add { fact } => ("add", None, fact.clone()),
+            MemoryOperation::Update { id, fact } => ("update", Some(id.as_str()), fact.clone()),
+            MemoryOperation::Delete { id } => ("delete", Some(id.as_str()), MemoryFact::new(&memory_content(&conn, id)?)),
+        };
+
+        conn.execute(
+            "INSERT INTO pending_memories (id, content, created_at, source_conversation_id, source_message_ids, extracted_by_model, extracted_at, operation, target_memory_id, category, importance, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                id,
+                fact.content,
+                Utc::now().to_rfc3339(),
+                source.conversation_id,
+                message_ids,
+                source.model,
+                source.extracted_at,
+                kind,
+                target,
+                fact.category.as_str(),
+                fact.importance,
+                fact.expires_at
+            ],
+        )
+        .map_err(|e| format!("Failed to queue memory: {}", e))?;
+        Ok(id)
+    }
+
+    pub fn list_pending_memories(&self) -> Result<Vec<PendingMemory>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, content, created_at, source_conversation_id, source_message_ids, extracted_by_model, extracted_at, operation, target_memory_id,
+                        category, importance, expires_at
+                 FROM pending_memories ORDER BY created_at ASC",
+            )
+            .map_err(|e| format!("Failed to prepare: {}", e))?;
+
+        let pending = stmt
+            .query_map([], |row| {
+                let message_ids: String = row.get(4)?;
+                Ok(PendingMemory {
+                    id: row.get(0)?,
+                    operation: row.get(7)?,
+                    target_memory_id: row.get(8)?,
+                    content: row.get(1)?,
+                    category: MemoryCategory::parse(&row.get::<_, String>(9)?),
+                    importance: row.get(10)?,
+                    expires_at: row.get(11)?,
+                    created_at: row.get(2)?,
+                    source: MemorySource {
+                        conversation_id: row.get(3)?,
+                        message_ids: serde_json::from_str(&message_ids).unwrap_or_default(),
+                        model: row.get(5)?,
+                        extracted_at: row.get(6)?,
+                    },
+                })
+            })
+            .map_err(|e| format!("Failed to query pending memories: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(pending)
+    }
+
+    /// The operation a pending memory proposes, optionally with its text replaced
+    /// (ignored for deletes)
+    pub fn pending_memory_operation(&self, id: &str, edited_content: Option<&str>) -> Result<MemoryOperation, String> {
+        let pending = self.pending_memory(id)?;
+        let fact = MemoryFact {
+            content: edited_content.map(str::to_string).unwrap_or(pending.content),
+            category: pending.category,
+            importance: pending.importance,
+            expires_at: pending.expires_at,
+        };
+        let target = pending.target_memory_id.unwrap_or_default();
+        Ok(match pending.operation.as_str() {
+            "update" => MemoryOperation::Update { id: target, fact },
+            "delete" => MemoryOperation::Delete { id: target },
+            _ => MemoryOperation::Add { fact },
+        })
+    }
+
+    /// Approve a pending memory by applying `op` (its proposed operation, possibly
+    /// edited or deduplicated). Provenance is carried over. Returns the affected memory ID.
+    pub fn approve_pending_memory(&self, id: &str, op: &MemoryOperation) -> Result<String, String> {
+        let pending = self.pending_memory(id)?;
+        let mut conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+        let memory_id = apply_memory_operation(&tx, op, Some(&pending.source))?;
+        tx.execute("DELETE FROM pending_memories WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to remove pending memory: {}", e))?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit approval: {}", e))?;
+        Ok(memory_id)
+    }
+
+    fn pending_memory(&self, id: &str) -> Result<PendingMemory, String> {
+        self.list_pending_memories()?
+            .into_iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| format!("Pending memory not found: {}", id))
+    }
+
+    pub fn reject_pending_memory(&self, id: &str) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute("DELETE FROM pending_memories WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to reject memory: {}", e))?;
+        Ok(())
+    }
+
+    pub fn get_memory_provenance(&self, id: &str) -> Result<MemoryProvenance, String> {
+        let (content, created_at, conversation_id, message_ids, model, extracted_at): (
+            String,
+            String,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+        ) = {
+            let conn = self.conn.lock().map_err(|e| e.to_string())?;
+            conn.query_row(
+                "SELECT content, created_at, source_conversation_id, source_message_ids, extracted_by_model, extracted_at
+                 FROM memories WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)),
+            )
+            .map_err(|e| format!("Memory not found: {}", e))?
+        };
+
+        let source = conversation_id.map(|conversation_id| MemorySource {
+            conversation_id,
+            message_ids: message_ids
+                .and_then(|ids| serde_json::from_str(&ids).ok())
+                .unwrap_or_default(),
+            model: model.unwrap_or_default(),
+            extracted_at: extracted_at.unwrap_or_default(),
+        });
+
+        let excerpt = match &source {
+            Some(src) => self.get_messages_by_ids(&src.message_ids)?,
+            None => Vec::new(),
+        };
+
+        Ok(MemoryProvenance {
+            memory_id: id.to_string(),
+            content,
+            created_at,
+            source,
+            excerpt,
+        })
+    }
+
+    pub fn list_memories(&self) -> Result<Vec<Memory>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare("SELECT id, content, created_at, category, importance, expires_at FROM memories ORDER BY created_at ASC")
             .map_err(|e| format!("Failed to prepare: {}", e))?;
 
         let memories = stmt
             .query_map([], |row| {
-                Ok((
-                    row.get::<_, String>(0)?,
-                    row.get::<_, String>(1)?,
-                    row.get::<_, String>(2)?,
-                ))
+                Ok(Memory {
+                    id: row.get(0)?,
+                    content: row.get(1)?,
+                    created_at: row.get(2)?,
+                    category: MemoryCategory::parse(&row.get::<_, String>(3)?),
+                    importance: row.get(4)?,
+                    expires_at: row.get(5)?,
+                })
             })
             .map_err(|e| format!("Failed to query memories: {}", e))?
             .filter_map(|r| r.ok())
@@ -239,24 +1737,406 @@ impl StorageEngine {
         Ok(memories)
     }
 
-    pub fn delete_memory(&self, id: &str) -> Result<(), String> {
+    /// `(id, content, embedding)` for every memory; the embedding is `None` if it is
+    /// missing or was computed with a different model
+    pub fn memory_embeddings(&self, model: &str) -> Result<Vec<MemoryEmbedding>, String> {
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
-        conn.execute("DELETE FROM memories WHERE id = ?1", params![id])
-            .map_err(|e| format!("Failed to delete memory: {}", e))?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, content, CASE WHEN embedding_model = ?1 THEN embedding END
+                 FROM memories ORDER BY created_at ASC",
+            )
+            .map_err(|e| format!("Failed to prepare: {}", e))?;
+
+        let rows = stmt
+            .query_map(params![model], |row| {
+                let embedding: Option<Vec<u8>> = row.get(2)?;
+                Ok((row.get(0)?, row.get(1)?, embedding.map(|b| decode_embedding(&b))))
+            })
+            .map_err(|e| format!("Failed to query: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(rows)
+    }
+
+    pub fn set_memory_embedding(&self, id: &str, model: &str, embedding: &[f32]) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE memories SET embedding = ?1, embedding_model = ?2 WHERE id = ?3",
+            params![encode_embedding(embedding), model, id],
+        )
+        .map_err(|e| format!("Failed to save embedding: {}", e))?;
         Ok(())
     }
 
-    /// Build a context string from all stored memories for injection into system prompt
+    /// Collapse several memories into the first one with `content`. Every memory
+    /// involved gets a 'merge' entry in its history.
+    pub fn merge_memories(&self, ids: &[String], content: &str) -> Result<String, String> {
+        let (keep, remove) = ids.split_first().ok_or("No memories to merge")?;
+        let mut conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        let now = Utc::now().to_rfc3339();
+        for id in ids {
+            let previous = memory_content(&tx, id)?;
+            tx.execute(
+                "INSERT INTO memory_versions (id, memory_id, content, change, changed_at) VALUES (?1, ?2, ?3, 'merge', ?4)",
+                params![Uuid::new_v4().to_string(), id, previous, now],
+            )
+            .map_err(|e| format!("Failed to record memory version: {}", e))?;
+        }
+
+        // The merged memory keeps the highest importance and the latest expiry of the group
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let (importance, expires_at): (u8, Option<String>) = tx
+            .query_row(
+                &format!(
+                    "SELECT MAX(importance), CASE WHEN COUNT(expires_at) = COUNT(*) THEN MAX(expires_at) END
+                     FROM memories WHERE id IN ({})",
+                    placeholders
+                ),
+                params_from_iter(ids),
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| format!("Failed to read memories: {}", e))?;
+
+        tx.execute(
+            "UPDATE memories SET content = ?1, importance = ?2, expires_at = ?3, embedding = NULL, embedding_model = NULL
+             WHERE id = ?4",
+            params![content, importance, expires_at, keep],
+        )
+        .map_err(|e| format!("Failed to update memory: {}", e))?;
+        for id in remove {
+            tx.execute("DELETE FROM memories WHERE id = ?1", params![id])
+                .map_err(|e| format!("Failed to delete memory: {}", e))?;
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit merge: {}", e))?;
+        Ok(keep.clone())
+    }
+
+    /// Delete a memory, keeping its last text in the version history
+    pub fn delete_memory(&self, id: &str) -> Result<(), String> {
+        self.save_memory_operation(&MemoryOperation::Delete { id: id.to_string() })?;
+        Ok(())
+    }
+
+    /// Build a context string from stored memories for injection into system prompt.
+    /// Expired memories are skipped and the most important ones come first.
     pub fn get_memory_context(&self) -> Result<String, String> {
-        let memories = self.list_memories()?;
+        let now = Utc::now();
+        let mut memories: Vec<Memory> = self
+            .list_memories()?
+            .into_iter()
+            .filter(|m| {
+                m.expires_at
+                    .as_deref()
+                    .and_then(|e| DateTime::parse_from_rfc3339(e).ok())
+                    .is_none_or(|e| e > now)
+            })
+            .collect();
+        // Stable sort keeps older memories first within the same importance
+        memories.sort_by_key(|m| std::cmp::Reverse(m.importance));
+        memories.truncate(MAX_CONTEXT_MEMORIES);
+
         if memories.is_empty() {
             return Ok(String::new());
         }
 
         let mut context = String::from("The following are long-term facts you know about the user. CRITICAL INSTRUCTION: You must strictly USE these facts to inform your answers, but NEVER arbitrarily mention them. Do not start responses with \"I know you are a...\" or \"Since you like...\". Only reference these facts if the user EXPLICITLY asks you about them or if they seamlessly resolve an ambiguity in the user's prompt:\n");
-        for (_id, content, _created_at) in &memories {
-            context.push_str(&format!("- {}\n", content));
+        for memory in &memories {
+            context.push_str(&format!("- {}\n", memory.content));
         }
         Ok(context)
     }
 }
+
+/// A `StorageEngine` in a fresh temporary directory. The directory is deleted when the
+/// fixture is dropped, including when a test fails.
+#[cfg(test)]
+pub(crate) struct TempStorage {
+    storage: Option<StorageEngine>,
+    dir: std::path::PathBuf,
+}
+
+#[cfg(test)]
+impl TempStorage {
+    pub(crate) fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("openworld-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Self { storage: Some(StorageEngine::open(&dir).unwrap()), dir }
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TempStorage {
+    type Target = StorageEngine;
+
+    fn deref(&self) -> &StorageEngine {
+        self.storage.as_ref().expect("storage is only taken on drop")
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempStorage {
+    fn drop(&mut self) {
+        // Close the database before deleting its directory
+        self.storage.take();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn test_list_conversations_filter() {
+        let storage = TempStorage::new();
+        let work = storage.create_conversation("Work: deploy plan", "m", MemoryMode::default(), None).unwrap();
+        let home = storage.create_conversation("Garden 50% off", "m", MemoryMode::default(), None).unwrap();
+        let old = storage.create_conversation("Old thread", "m", MemoryMode::default(), None).unwrap();
+
+        let folder = storage.create_folder("Work").unwrap();
+        storage.move_conversation_to_folder(&work.id, Some(&folder.id)).unwrap();
+        let tag = storage.create_tag("ops").unwrap();
+        storage.tag_conversation(&work.id, &tag.id).unwrap();
+        storage.set_conversation_pinned(&home.id, true).unwrap();
+        storage.set_conversation_archived(&old.id, true).unwrap();
+
+        let active = storage
+            .list_conversations(&ConversationFilter { archived: Some(false), ..Default::default() })
+            .unwrap();
+        assert_eq!(active.len(), 2);
+        assert_eq!(active[0].id, home.id, "pinned conversations come first");
+
+        let by_tag = storage
+            .list_conversations(&ConversationFilter { tag_id: Some(tag.id.clone()), ..Default::default() })
+            .unwrap();
+        assert_eq!(by_tag.len(), 1);
+        assert_eq!(by_tag[0].folder_id.as_deref(), Some(folder.id.as_str()));
+        assert_eq!(by_tag[0].tags[0].name, "ops");
+
+        let search = storage
+            .list_conversations(&ConversationFilter { search: Some("50%".to_string()), ..Default::default() })
+            .unwrap();
+        assert_eq!(search.len(), 1);
+        assert_eq!(search[0].id, home.id);
+
+        let page = storage
+            .list_conversations(&ConversationFilter { limit: Some(1), offset: Some(1), ..Default::default() })
+            .unwrap();
+        assert_eq!(page.len(), 1);
+    }
+
+    #[test]
+    fn test_edit_message_truncates_in_insertion_order() {
+        let storage = TempStorage::new();
+        let convo = storage.create_conversation("Imported", "m", MemoryMode::default(), None).unwrap();
+        // Imports can carry identical timestamps, or offsets that don't sort as text
+        let messages = [
+            ("user".to_string(), "first".to_string(), "2024-05-01T10:00:00+00:00".to_string()),
+            ("assistant".to_string(), "reply".to_string(), "2024-05-01T10:00:00+00:00".to_string()),
+            ("user".to_string(), "second".to_string(), "2024-05-01T09:30:00-02:00".to_string()),
+            ("assistant".to_string(), "reply 2".to_string(), "2024-05-01T09:00:00Z".to_string()),
+        ];
+        let imported = Conversation { id: Uuid::new_v4().to_string(), ..convo };
+        assert!(storage.import_conversation("chatgpt", "ext-edit", &imported, &messages).unwrap());
+
+        let before = storage.get_messages(&imported.id).unwrap();
+        let contents: Vec<&str> = before.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["first", "reply", "second", "reply 2"]);
+        storage.edit_message(&before[2].id, "second, edited").unwrap();
+        storage.edit_message(&before[0].id, "first, edited").unwrap();
+
+        let after = storage.get_messages(&imported.id).unwrap();
+        assert_eq!(after.len(), 1);
+        assert_eq!(after[0].content, "first, edited");
+        assert_eq!(storage.get_message_edits(&before[0].id).unwrap().len(), 1);
+        // The follow-ups' edit history went with them
+        assert!(storage.get_message_edits(&before[2].id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trash_restore_and_purge() {
+        let storage = TempStorage::new();
+        let convo = storage.create_conversation("Secret plans", "m", MemoryMode::default(), None).unwrap();
+        storage.add_message(&convo.id, "user", "hello").unwrap();
+
+        storage.delete_conversation(&convo.id).unwrap();
+        assert!(storage.list_conversations(&ConversationFilter::default()).unwrap().is_empty());
+        let trash = storage
+            .list_conversations(&ConversationFilter { in_trash: true, ..Default::default() })
+            .unwrap();
+        assert_eq!(trash.len(), 1);
+        assert!(trash[0].deleted_at.is_some());
+
+        storage.restore_conversation(&convo.id).unwrap();
+        assert_eq!(storage.list_conversations(&ConversationFilter::default()).unwrap().len(), 1);
+
+        storage.delete_conversation(&convo.id).unwrap();
+        assert_eq!(storage.empty_trash().unwrap(), 1);
+        assert!(storage.get_conversation(&convo.id).unwrap().is_none());
+        assert!(storage.get_messages(&convo.id).unwrap().is_empty());
+
+        // A purged import isn't imported again
+        let imported = Conversation { id: Uuid::new_v4().to_string(), ..convo };
+        let messages = [("user".to_string(), "hi".to_string(), imported.created_at.clone())];
+        assert!(storage.import_conversation("chatgpt", "ext-1", &imported, &messages).unwrap());
+        storage.purge_conversation(&imported.id).unwrap();
+        assert!(!storage.import_conversation("chatgpt", "ext-1", &imported, &messages).unwrap());
+    }
+
+    #[test]
+    fn test_memory_operations_keep_history() {
+        let storage = TempStorage::new();
+        let source = MemorySource {
+            conversation_id: "c1".to_string(),
+            message_ids: vec!["m1".to_string()],
+            model: "llama3:8b".to_string(),
+            extracted_at: Utc::now().to_rfc3339(),
+        };
+        storage
+            .apply_memory_operations(&[MemoryOperation::Add { fact: MemoryFact::new("Lives in Berlin") }], &source)
+            .unwrap();
+        let id = storage.list_memories().unwrap().remove(0).id;
+
+        let summary = storage
+            .apply_memory_operations(
+                &[MemoryOperation::Update { id: id.clone(), fact: MemoryFact::new("Lives in Lisbon") }],
+                &source,
+            )
+            .unwrap();
+        assert_eq!(summary.updated, 1);
+        assert_eq!(storage.list_memories().unwrap()[0].content, "Lives in Lisbon");
+
+        // A failing operation rolls back the whole batch
+        let result = storage.apply_memory_operations(
+            &[
+                MemoryOperation::Delete { id: id.clone() },
+                MemoryOperation::Delete { id: "missing".to_string() },
+            ],
+            &source,
+        );
+        assert!(result.is_err());
+        assert_eq!(storage.list_memories().unwrap().len(), 1);
+
+        let history = storage.get_memory_history(&id).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].content, "Lives in Berlin");
+
+        // A change made by the user keeps the memory's provenance
+        storage
+            .save_memory_operation(&MemoryOperation::Update { id: id.clone(), fact: MemoryFact::new("Lives in Porto") })
+            .unwrap();
+        assert_eq!(storage.list_memories().unwrap()[0].content, "Lives in Porto");
+        assert_eq!(storage.get_memory_provenance(&id).unwrap().source.unwrap().conversation_id, "c1");
+        assert_eq!(storage.get_memory_history(&id).unwrap().len(), 2);
+
+        storage.delete_memory(&id).unwrap();
+        assert!(storage.list_memories().unwrap().is_empty());
+        let history = storage.get_memory_history(&id).unwrap();
+        assert_eq!((history[2].change.as_str(), history[2].content.as_str()), ("delete", "Lives in Porto"));
+    }
+
+    #[test]
+    fn test_memory_context_prioritizes_and_skips_expired() {
+        let storage = TempStorage::new();
+        storage.add_memory(&MemoryFact::new("Likes tea")).unwrap();
+        storage
+            .add_memory(&MemoryFact { importance: 5, category: MemoryCategory::Health, ..MemoryFact::new("Allergic to peanuts") })
+            .unwrap();
+        storage
+            .add_memory(&MemoryFact {
+                expires_at: Some((Utc::now() - chrono::Duration::days(1)).to_rfc3339()),
+                ..MemoryFact::new("Is visiting Rome this week")
+            })
+            .unwrap();
+
+        let context = storage.get_memory_context().unwrap();
+        let peanuts = context.find("Allergic to peanuts").unwrap();
+        let tea = context.find("Likes tea").unwrap();
+        assert!(peanuts < tea);
+        assert!(!context.contains("Rome"));
+    }
+
+    #[test]
+    fn test_merge_memories() {
+        let storage = TempStorage::new();
+        let a = storage.add_memory(&MemoryFact::new("Is a software engineer")).unwrap();
+        let b = storage
+            .add_memory(&MemoryFact { importance: 4, ..MemoryFact::new("Works as a software developer") })
+            .unwrap();
+        storage.set_memory_embedding(&a, "embed", &[0.5, -1.0]).unwrap();
+        assert_eq!(storage.memory_embeddings("embed").unwrap()[0].2, Some(vec![0.5, -1.0]));
+        assert_eq!(storage.memory_embeddings("other").unwrap()[0].2, None);
+
+        storage
+            .merge_memories(&[a.clone(), b.clone()], "Works as a software engineer")
+            .unwrap();
+        let memories = storage.list_memories().unwrap();
+        assert_eq!(memories.len(), 1);
+        assert_eq!(memories[0].id, a);
+        assert_eq!(memories[0].importance, 4);
+        assert_eq!(storage.get_memory_history(&b).unwrap()[0].change, "merge");
+    }
+
+    #[test]
+    fn test_persona_attached_to_conversation() {
+        let storage = TempStorage::new();
+        let persona = storage
+            .create_persona(&PersonaDraft {
+                name: "Spanish tutor".to_string(),
+                system_prompt: "Reply only in Spanish.".to_string(),
+                options: GenerationOptions { temperature: Some(0.3), ..Default::default() },
+                memory_mode: MemoryMode::ReadOnly,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(storage.get_persona(&persona.id).unwrap().unwrap().options.temperature, Some(0.3));
+
+        let convo = storage
+            .create_conversation("Hola", "m", persona.memory_mode, Some(&persona.id))
+            .unwrap();
+        let loaded = storage.get_conversation(&convo.id).unwrap().unwrap();
+        assert_eq!(loaded.persona_id.as_deref(), Some(persona.id.as_str()));
+        assert_eq!(loaded.memory_mode, MemoryMode::ReadOnly);
+
+        storage.delete_persona(&persona.id).unwrap();
+        assert!(storage.get_conversation(&convo.id).unwrap().unwrap().persona_id.is_none());
+    }
+
+    #[test]
+    fn test_prompt_template_crud() {
+        let storage = TempStorage::new();
+        let draft = PromptTemplateDraft {
+            name: " Code review ".to_string(),
+            content: "Review this {{lang}} diff:\n{{diff}}".to_string(),
+            default_model: Some("qwen2.5-coder:7b".to_string()),
+            options: GenerationOptions { temperature: Some(0.2), ..Default::default() },
+        };
+        let template = storage.create_prompt_template(&draft).unwrap();
+        assert_eq!(template.name, "Code review");
+        assert_eq!(template.variables, vec!["lang".to_string(), "diff".to_string()]);
+        assert!(storage
+            .create_prompt_template(&PromptTemplateDraft { name: "  ".to_string(), ..draft.clone() })
+            .is_err());
+
+        let updated = PromptTemplateDraft { name: "Diff review".to_string(), default_model: None, ..draft };
+        storage.update_prompt_template(&template.id, &updated).unwrap();
+        let loaded = storage.get_prompt_template(&template.id).unwrap().unwrap();
+        assert_eq!(loaded.name, "Diff review");
+        assert_eq!(loaded.default_model, None);
+        assert_eq!(loaded.options.temperature, Some(0.2));
+        assert!(storage.update_prompt_template("missing", &updated).is_err());
+
+        assert_eq!(storage.list_prompt_templates().unwrap().len(), 1);
+        storage.delete_prompt_template(&template.id).unwrap();
+        assert!(storage.list_prompt_templates().unwrap().is_empty());
+        assert!(storage.get_prompt_template(&template.id).unwrap().is_none());
+    }
+}
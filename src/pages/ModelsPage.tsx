@@ -1,15 +1,23 @@
 import { useEffect, useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { listen } from '@tauri-apps/api/event';
 import {
     useModelStore,
     MODEL_CATALOG,
     getRamCompatibility,
+    pullModel,
     type ModelInfo,
+    type OllamaError,
 } from '../stores/modelStore';
 import { useSettingsStore } from '../stores/settingsStore';
 import './ModelsPage.css';
 
+/** Message of a failed pull (an `Error`) or model command (an `OllamaError`) */
+function errorMessage(err: unknown): string {
+    if (err instanceof Error) return err.message;
+    if (typeof err === 'string') return err;
+    return (err as OllamaError)?.message || 'Something went wrong. Please try again.';
+}
+
 export default function ModelsPage() {
     const {
         installedModels,
@@ -23,6 +31,7 @@ export default function ModelsPage() {
     } = useModelStore();
     const { defaultModel, setDefaultModel } = useSettingsStore();
     const [deleteConfirm, setDeleteConfirm] = useState<string | null>(null);
+    const [error, setError] = useState<string | null>(null);
 
     useEffect(() => {
         loadModels();
@@ -36,6 +45,7 @@ export default function ModelsPage() {
             setInstalledModels(models);
         } catch (err) {
             console.error('Failed to load models:', err);
+            setError(errorMessage(err));
         }
         setIsLoading(false);
     }
@@ -44,34 +54,35 @@ export default function ModelsPage() {
         console.log('[openworld] Pulling model:', modelId);
         setPullingModel(modelId);
         setPullProgress({ status: 'Starting...' });
-
-        const unlisten = await listen<{ status: string; total?: number; completed?: number }>(
-            'model-pull-progress',
-            (event) => {
-                setPullProgress(event.payload);
-            }
-        );
+        setError(null);
 
         try {
-            await invoke('pull_model', { modelName: modelId });
+            await pullModel(modelId, (pull) => setPullProgress({
+                status: pull.status,
+                total: pull.total ?? undefined,
+                completed: pull.completed ?? undefined,
+            }));
             console.log('[openworld] Pull completed for:', modelId);
             await loadModels();
         } catch (err) {
             console.error('[openworld] Pull failed:', err);
+            setError(errorMessage(err));
         } finally {
             setPullingModel(null);
             setPullProgress(null);
-            unlisten();
         }
     }
 
     async function handleDelete(modelName: string) {
+        setError(null);
         try {
             await invoke('delete_model', { modelName });
             await loadModels();
             setDeleteConfirm(null);
         } catch (err) {
             console.error('Delete failed:', err);
+            setError(errorMessage(err));
+            setDeleteConfirm(null);
         }
     }
 
@@ -103,6 +114,15 @@ export default function ModelsPage() {
                 <p>Choose and manage your AI models</p>
             </div>
 
+            {error && (
+                <div className="models-error" role="alert">
+                    <span>{error}</span>
+                    <button className="btn btn-ghost" onClick={() => setError(null)}>
+                        Dismiss
+                    </button>
+                </div>
+            )}
+
             {/* Installed Models */}
             {installedModels.length > 0 && (
                 <section className="models-section">
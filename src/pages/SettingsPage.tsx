@@ -17,6 +17,24 @@ interface SystemMetrics {
     db_size_bytes: number;
 }
 
+interface EngineConfig {
+    ollama_host: string;
+    use_external_engine: boolean;
+    pinned_engine_version: string | null;
+    engine_port: number | null;
+    engine_models_dir: string | null;
+    engine_num_parallel: number | null;
+    engine_max_loaded_models: number | null;
+    engine_keep_alive: string | null;
+    engine_context_length: number | null;
+}
+
+/** Empty number fields mean "use the engine's default" */
+function optionalNumber(value: string): number | null {
+    const n = parseInt(value, 10);
+    return Number.isNaN(n) ? null : n;
+}
+
 export default function SettingsPage() {
     const { theme, setTheme, defaultModel, setDefaultModel, systemPrompt, setSystemPrompt } =
         useSettingsStore();
@@ -26,12 +44,21 @@ export default function SettingsPage() {
     const [newMemory, setNewMemory] = useState('');
     const [activeTab, setActiveTab] = useState<'general' | 'advanced'>('general');
     const [sysMetrics, setSysMetrics] = useState<SystemMetrics | null>(null);
+    const [engineConfig, setEngineConfig] = useState<EngineConfig | null>(null);
+    const [engineSaving, setEngineSaving] = useState(false);
+    const [engineSaved, setEngineSaved] = useState(false);
+    const [engineError, setEngineError] = useState<string | null>(null);
+    const [engineUpdating, setEngineUpdating] = useState(false);
+    const [engineVersion, setEngineVersion] = useState<string | null>(null);
 
     useEffect(() => {
         invoke<any[]>('list_models')
             .then(setInstalledModels)
             .catch(console.error);
         loadMemories();
+        invoke<EngineConfig>('get_engine_config')
+            .then(setEngineConfig)
+            .catch(console.error);
 
         // Polling loop for live system metrics
         let interval: ReturnType<typeof setInterval>;
@@ -78,13 +105,14 @@ export default function SettingsPage() {
 
     async function handleSave() {
         try {
+            const config = await invoke<any>('get_config');
             await invoke('save_config_cmd', {
                 cfg: {
+                    ...config,
                     theme,
                     default_model: defaultModel,
                     setup_complete: true,
                     system_prompt: systemPrompt,
-                    ollama_host: 'http://localhost:11434',
                 },
             });
             setSaved(true);
@@ -94,6 +122,41 @@ export default function SettingsPage() {
         }
     }
 
+    function updateEngineConfig(changes: Partial<EngineConfig>) {
+        setEngineConfig((current) => (current ? { ...current, ...changes } : current));
+    }
+
+    async function handleSaveEngine() {
+        if (!engineConfig) return;
+        setEngineSaving(true);
+        setEngineError(null);
+        try {
+            const applied = await invoke<EngineConfig>('save_engine_config', { cfg: engineConfig });
+            setEngineConfig(applied);
+            setEngineSaved(true);
+            setTimeout(() => setEngineSaved(false), 2000);
+        } catch (err) {
+            setEngineError(String(err));
+        } finally {
+            setEngineSaving(false);
+        }
+    }
+
+    /** Save the settings, then install the pinned version (or the latest one) */
+    async function handleUpdateEngine() {
+        if (!engineConfig) return;
+        setEngineUpdating(true);
+        setEngineError(null);
+        try {
+            setEngineConfig(await invoke<EngineConfig>('save_engine_config', { cfg: engineConfig }));
+            setEngineVersion(await invoke<string>('update_engine'));
+        } catch (err) {
+            setEngineError(String(err));
+        } finally {
+            setEngineUpdating(false);
+        }
+    }
+
     function handleThemeToggle() {
         const newTheme = theme === 'dark' ? 'light' : 'dark';
         setTheme(newTheme);
@@ -251,6 +314,152 @@ export default function SettingsPage() {
                 </section>
 
                 {/* ADVANCED TAB */}
+                {activeTab === 'advanced' && engineConfig && (
+                    <section className="settings-section card">
+                        <h3 className="settings-section-title">
+                            <svg width="18" height="18" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2">
+                                <rect x="2" y="3" width="20" height="8" rx="2" />
+                                <rect x="2" y="13" width="20" height="8" rx="2" />
+                                <path d="M6 7h.01M6 17h.01" />
+                            </svg>
+                            AI Engine
+                        </h3>
+                        <div className="engine-settings">
+                            <div className="setting-row">
+                                <div className="setting-info">
+                                    <span className="setting-label">Engine address</span>
+                                    <span className="setting-desc">Where OpenWorld reaches the AI engine</span>
+                                </div>
+                                <input
+                                    className="input engine-input"
+                                    value={engineConfig.ollama_host}
+                                    onChange={(e) => updateEngineConfig({ ollama_host: e.target.value })}
+                                />
+                            </div>
+                            <div className="setting-row">
+                                <div className="setting-info">
+                                    <span className="setting-label">Use an Ollama that's already running</span>
+                                    <span className="setting-desc">
+                                        Connect to an Ollama you installed yourself at the address above instead of starting the built-in engine
+                                    </span>
+                                </div>
+                                <input
+                                    type="checkbox"
+                                    checked={engineConfig.use_external_engine}
+                                    onChange={(e) => updateEngineConfig({ use_external_engine: e.target.checked })}
+                                />
+                            </div>
+                            <div className="setting-row">
+                                <div className="setting-info">
+                                    <span className="setting-label">Engine version</span>
+                                    <span className="setting-desc">
+                                        Stay on a specific Ollama release, e.g. v0.5.7. Leave empty to follow the latest release.
+                                        {engineVersion && ` Installed: ${engineVersion}.`}
+                                    </span>
+                                </div>
+                                <div className="engine-version">
+                                    <input
+                                        className="input engine-input"
+                                        placeholder="Latest"
+                                        value={engineConfig.pinned_engine_version ?? ''}
+                                        onChange={(e) => updateEngineConfig({ pinned_engine_version: e.target.value || null })}
+                                    />
+                                    <button className="btn btn-secondary" onClick={handleUpdateEngine} disabled={engineUpdating || engineSaving}>
+                                        {engineUpdating ? 'Installing...' : 'Install'}
+                                    </button>
+                                </div>
+                            </div>
+                            <div className="setting-row">
+                                <div className="setting-info">
+                                    <span className="setting-label">Port</span>
+                                    <span className="setting-desc">Leave empty to pick a free port automatically</span>
+                                </div>
+                                <input
+                                    className="input engine-input"
+                                    type="number"
+                                    min={1}
+                                    max={65535}
+                                    placeholder="Automatic"
+                                    value={engineConfig.engine_port ?? ''}
+                                    onChange={(e) => updateEngineConfig({ engine_port: optionalNumber(e.target.value) })}
+                                />
+                            </div>
+                            <div className="setting-row">
+                                <div className="setting-info">
+                                    <span className="setting-label">Models folder</span>
+                                    <span className="setting-desc">Where downloaded models are stored</span>
+                                </div>
+                                <input
+                                    className="input engine-input"
+                                    placeholder="~/.ollama/models"
+                                    value={engineConfig.engine_models_dir ?? ''}
+                                    onChange={(e) => updateEngineConfig({ engine_models_dir: e.target.value || null })}
+                                />
+                            </div>
+                            <div className="setting-row">
+                                <div className="setting-info">
+                                    <span className="setting-label">Parallel requests</span>
+                                    <span className="setting-desc">Requests each loaded model answers at the same time</span>
+                                </div>
+                                <input
+                                    className="input engine-input"
+                                    type="number"
+                                    min={1}
+                                    placeholder="Default"
+                                    value={engineConfig.engine_num_parallel ?? ''}
+                                    onChange={(e) => updateEngineConfig({ engine_num_parallel: optionalNumber(e.target.value) })}
+                                />
+                            </div>
+                            <div className="setting-row">
+                                <div className="setting-info">
+                                    <span className="setting-label">Models kept loaded</span>
+                                    <span className="setting-desc">How many models can be in memory at once</span>
+                                </div>
+                                <input
+                                    className="input engine-input"
+                                    type="number"
+                                    min={1}
+                                    placeholder="Default"
+                                    value={engineConfig.engine_max_loaded_models ?? ''}
+                                    onChange={(e) => updateEngineConfig({ engine_max_loaded_models: optionalNumber(e.target.value) })}
+                                />
+                            </div>
+                            <div className="setting-row">
+                                <div className="setting-info">
+                                    <span className="setting-label">Keep idle models loaded for</span>
+                                    <span className="setting-desc">For example 5m or 1h; -1 keeps them loaded</span>
+                                </div>
+                                <input
+                                    className="input engine-input"
+                                    placeholder="5m"
+                                    value={engineConfig.engine_keep_alive ?? ''}
+                                    onChange={(e) => updateEngineConfig({ engine_keep_alive: e.target.value || null })}
+                                />
+                            </div>
+                            <div className="setting-row">
+                                <div className="setting-info">
+                                    <span className="setting-label">Context length</span>
+                                    <span className="setting-desc">Default context window in tokens</span>
+                                </div>
+                                <input
+                                    className="input engine-input"
+                                    type="number"
+                                    min={1}
+                                    placeholder="Default"
+                                    value={engineConfig.engine_context_length ?? ''}
+                                    onChange={(e) => updateEngineConfig({ engine_context_length: optionalNumber(e.target.value) })}
+                                />
+                            </div>
+                        </div>
+                        {engineError && <p className="engine-error">{engineError}</p>}
+                        <div className="settings-save">
+                            <button className="btn btn-primary" onClick={handleSaveEngine} disabled={engineSaving || engineUpdating}>
+                                {engineSaving ? 'Restarting engine...' : engineSaved ? '✓ Saved!' : 'Save & Restart Engine'}
+                            </button>
+                        </div>
+                    </section>
+                )}
+
                 {activeTab === 'advanced' && (
                     <section className="settings-section card observability-panel">
                         <h3 className="settings-section-title">
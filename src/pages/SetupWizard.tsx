@@ -2,7 +2,7 @@ import { useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import { useSettingsStore } from '../stores/settingsStore';
-import { MODEL_CATALOG, useModelStore, getRamCompatibility, type ModelCatalogEntry } from '../stores/modelStore';
+import { MODEL_CATALOG, useModelStore, getRamCompatibility, pullModel, type ModelCatalogEntry } from '../stores/modelStore';
 import './SetupWizard.css';
 
 type Step = 'welcome' | 'preparing' | 'pick-model' | 'downloading' | 'ready';
@@ -11,6 +11,8 @@ interface OllamaStatus {
     stage: string;
     message: string;
     progress: number | null;
+    version?: string | null;
+    owner?: 'managed' | 'external' | null;
 }
 
 export default function SetupWizard() {
@@ -79,25 +81,17 @@ export default function SetupWizard() {
         setDownloadProgress(0);
         setDownloadStatus('Starting download...');
 
-        const unlisten = await listen<{ status: string; total?: number; completed?: number }>(
-            'model-pull-progress',
-            (event) => {
-                const { status, total, completed } = event.payload;
-                setDownloadStatus(status);
-                if (total && completed) {
-                    setDownloadProgress(Math.round((completed / total) * 100));
-                }
-            }
-        );
-
         try {
-            await invoke('pull_model', { modelName: selectedModel.id });
+            await pullModel(selectedModel.id, (pull) => {
+                setDownloadStatus(pull.status);
+                if (pull.percent != null) {
+                    setDownloadProgress(Math.round(pull.percent));
+                }
+            });
             setStep('ready');
         } catch (err) {
             console.error('Download failed:', err);
-            setDownloadStatus(`Download failed. Please check your internet connection and try again.`);
-        } finally {
-            unlisten();
+            setDownloadStatus(err instanceof Error ? err.message : `Download failed. Please check your internet connection and try again.`);
         }
     }
 
@@ -107,13 +101,14 @@ export default function SetupWizard() {
         }
         setSetupComplete(true);
         try {
+            const config = await invoke<any>('get_config');
             await invoke('save_config_cmd', {
                 cfg: {
+                    ...config,
                     theme: 'dark',
                     default_model: selectedModel?.id || 'llama3:8b',
                     setup_complete: true,
                     system_prompt: '',
-                    ollama_host: 'http://localhost:11434',
                 },
             });
         } catch (err) {
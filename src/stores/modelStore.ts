@@ -1,4 +1,6 @@
 import { create } from 'zustand';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 
 export interface ModelInfo {
     name: string;
@@ -13,6 +15,25 @@ export interface ModelInfo {
     };
 }
 
+export interface PullInfo {
+    id: string;
+    model: string;
+    state: 'queued' | 'downloading' | 'completed' | 'failed' | 'cancelled';
+    status: string;
+    completed?: number | null;
+    total?: number | null;
+    percent?: number | null;
+    bytes_per_sec?: number | null;
+    eta_secs?: number | null;
+    error?: OllamaError | null;
+}
+
+/** Error returned by model commands and carried by failed pulls */
+export interface OllamaError {
+    kind: 'not_found' | 'disk_full' | 'network' | 'unauthorized_registry' | 'engine_not_running' | 'other';
+    message: string;
+}
+
 export interface ModelCatalogEntry {
     id: string;
     friendlyName: string;
@@ -117,3 +138,40 @@ export function getRamCompatibility(required: number, available: number): 'good'
     if (available >= required) return 'tight';
     return 'insufficient';
 }
+
+/**
+ * Queue a model pull and resolve once it finishes. Rejects with the error message if it
+ * fails or is cancelled; `onProgress` receives progress for this pull only.
+ */
+export async function pullModel(modelName: string, onProgress: (pull: PullInfo) => void): Promise<PullInfo> {
+    let pullId: string | null = null;
+    let settle: (pull: PullInfo) => void = () => {};
+    const finished = new Promise<PullInfo>((resolve) => {
+        settle = resolve;
+    });
+    // A pull can finish before `pull_model` returns its id (e.g. an instant failure)
+    const finishedById = new Map<string, PullInfo>();
+
+    const unlistenProgress = await listen<PullInfo>('model-pull-progress', (event) => {
+        if (event.payload.id === pullId) onProgress(event.payload);
+    });
+    const unlistenFinished = await listen<PullInfo>('model-pull-finished', (event) => {
+        finishedById.set(event.payload.id, event.payload);
+        if (event.payload.id === pullId) settle(event.payload);
+    });
+
+    try {
+        const id = await invoke<string>('pull_model', { modelName });
+        pullId = id;
+        const already = finishedById.get(id);
+        if (already) settle(already);
+        const result = await finished;
+        if (result.state !== 'completed') {
+            throw new Error(result.error?.message || `Pull ${result.state}`);
+        }
+        return result;
+    } finally {
+        unlistenProgress();
+        unlistenFinished();
+    }
+}